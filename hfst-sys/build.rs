@@ -14,16 +14,28 @@ fn main() -> Result<(), ()> {
     }
 
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=wrapper.hpp");
+    println!("cargo:rerun-if-changed=shim/hfst_shim.h");
+    println!("cargo:rerun-if-changed=shim/hfst_shim.cpp");
 
+    // The shim is written against the C++ API of libhfst, so only libhfst
+    // itself is needed, see "The shim" in the README.
     let hfst_lib = pkg_config::Config::new()
-        .atleast_version("0.0.0")
-        .probe("hfst_c")
-        .map_err(|e| panic!("{:?}", e))?;
+        .cargo_metadata(false)
+        .probe("hfst")
+        .map_err(|e| panic!("hfst-sys needs libhfst: {e:?}"))?;
 
-    for include_path in hfst_lib.include_paths {
+    let mut shim = cc::Build::new();
+    shim.cpp(true).std("c++17").file("shim/hfst_shim.cpp");
+    for include_path in &hfst_lib.include_paths {
+        shim.include(include_path);
         println!("cargo:rerun-if-changed={}", include_path.display());
     }
+    // this also links the C++ standard library
+    shim.compile("hfst_shim");
 
+    // libhfst must come after the shim on the linker command line, so its
+    // flags are printed here, and not by pkg-config when probing
     for lib_dir in hfst_lib.link_paths {
         println!("cargo:rustc-link-search={}", lib_dir.display());
     }
@@ -32,8 +44,6 @@ fn main() -> Result<(), ()> {
         println!("cargo:rustc-link-lib={lib}");
     }
 
-    //println!("cargo:rustc-link-search=/home/anders/projects/hfst/local_install/lib");
-
     // The bindgen::Builder is the main entry point
     // to bindgen, and lets you build up options for
     // the resulting bindings.
@@ -47,30 +57,27 @@ fn main() -> Result<(), ()> {
         // .hpp wrapper, so it understands "extern C"", etc
         //.header("/usr/include/hfst/hfst.h")
         .header("wrapper.hpp")
-        //.allowlist_item("NOT_TRANSDUCER_STREAM")
-        //.allowlist_item("END_OF_STREAM")
-        //.allowlist_item("IMPLEMENTATION_TYPE_NOT_AVAILABLE")
-        //.allowlist_item("OTHER")
-        .allowlist_item("hfst_free")
-        .allowlist_item("hfst_empty_transducer")
-        .allowlist_item("hfst_input_stream")
-        .allowlist_item("hfst_input_stream_close")
-        .allowlist_item("hfst_input_stream_free")
-        .allowlist_item("hfst_input_stream_is_eof")
-        .allowlist_item("hfst_input_stream_is_bad")
-        .allowlist_item("hfst_transducer_from_stream")
-        .allowlist_item("hfst_lookup_begin")
-        //.allowlist_item("hfst_lookup_results")
-        .allowlist_item("hfst_lookup")
-        .allowlist_item("hfst_lookup_iterator")
-        .allowlist_item("hfst_lookup_iterator_value")
-        .allowlist_item("hfst_lookup_iterator_next")
-        .allowlist_item("hfst_lookup_iterator_free")
-        .allowlist_item("hfst_lookup_iterator_done")
-        //.allowlist_function("hfst_input_stream_from_file")
-        //.allowlist_function("hfst_input_stream_free")
-        .allowlist_item("hfst_tokenizer_open")
-        .allowlist_item("hfst_tokenizer_tokenize")
+        .allowlist_item("NOT_TRANSDUCER_STREAM")
+        .allowlist_item("END_OF_STREAM")
+        .allowlist_item("IMPLEMENTATION_TYPE_NOT_AVAILABLE")
+        .allowlist_item("OTHER")
+        .allowlist_item("TRANSDUCER_TYPE_MISMATCH")
+        .allowlist_item("NOT_DETERMINIZABLE")
+        .allowlist_item("TRANSDUCER_IS_CYCLIC")
+        .allowlist_item("NOT_VALID_ATT_FORMAT")
+        .allowlist_item("XRE_COMPILE_ERROR")
+        .allowlist_item("TWOLC_COMPILE_ERROR")
+        .allowlist_item("NOT_OPTIMIZED_FOR_LOOKUP")
+        .allowlist_item(".*_TYPE")
+        .allowlist_item("TO_INITIAL_STATE")
+        .allowlist_item("TO_FINAL_STATE")
+        .allowlist_item("UNKNOWN_SYMBOLS_.*")
+        .allowlist_item("HfstTransducerStats")
+        .allowlist_item("HfstLookupOptions")
+        .allowlist_item("HfstTraceArc")
+        .allowlist_item("ResultIterator")
+        // every function of the shim
+        .allowlist_item("hfst_.*")
 
         // Tell cargo to invalidate the built crate whenever any of the
         // included header files changed.
@@ -0,0 +1,1651 @@
+// The C API of hfst_shim.h, implemented on top of the libhfst C++ API.
+
+#include <hfst/hfst.h>
+#include <hfst/parsers/TwolcCompiler.h>
+#include <hfst/parsers/XreCompiler.h>
+
+#include <unistd.h>
+
+#include <algorithm>
+#include <chrono>
+#include <cstdio>
+#include <cstdlib>
+#include <cstring>
+#include <deque>
+#include <limits>
+#include <map>
+#include <memory>
+#include <mutex>
+#include <set>
+#include <sstream>
+#include <string>
+#include <utility>
+#include <vector>
+
+// This comes last: Its error codes and implementation types are macros, that
+// would otherwise replace the enumerators of the same names in the libhfst
+// headers. Below, the enumerators are always reached through the macros.
+#include "hfst_shim.h"
+
+using hfst::HfstInputStream;
+using hfst::HfstOutputStream;
+using hfst::HfstTransducer;
+using hfst::ImplementationType;
+using hfst::implementations::HfstBasicTransducer;
+using hfst::implementations::HfstBasicTransition;
+
+struct ResultIterator;
+
+namespace hfst_shim {
+
+const char EPSILON[] = "@_EPSILON_SYMBOL_@";
+const char UNKNOWN[] = "@_UNKNOWN_SYMBOL_@";
+const char IDENTITY[] = "@_IDENTITY_SYMBOL_@";
+
+// An error that the shim gives by itself, rather than libhfst.
+struct Error {
+    int code;
+};
+
+void set_err(int *err, int code) {
+    if (err != nullptr) {
+        *err = code;
+    }
+}
+
+// Run `f`, and turn the exceptions that it throws into the error codes of
+// hfst_shim.h.
+template <typename F> void guard(int *err, F f) {
+    set_err(err, 0);
+    try {
+        f();
+    } catch (const Error &e) {
+        set_err(err, e.code);
+    } catch (const NotTransducerStreamException &) {
+        set_err(err, NOT_TRANSDUCER_STREAM);
+    } catch (const EndOfStreamException &) {
+        set_err(err, END_OF_STREAM);
+    } catch (const ImplementationTypeNotAvailableException &) {
+        set_err(err, IMPLEMENTATION_TYPE_NOT_AVAILABLE);
+    } catch (const TransducerTypeMismatchException &) {
+        set_err(err, TRANSDUCER_TYPE_MISMATCH);
+    } catch (const TransducerIsCyclicException &) {
+        set_err(err, TRANSDUCER_IS_CYCLIC);
+    } catch (const NotValidAttFormatException &) {
+        set_err(err, NOT_VALID_ATT_FORMAT);
+    } catch (...) {
+        set_err(err, OTHER);
+    }
+}
+
+HfstTransducer *as_transducer(void *transducer) {
+    return static_cast<HfstTransducer *>(transducer);
+}
+
+const HfstTransducer *as_transducer(const void *transducer) {
+    return static_cast<const HfstTransducer *>(transducer);
+}
+
+ImplementationType as_type(int implementation_type) {
+    return static_cast<ImplementationType>(implementation_type);
+}
+
+bool is_optimized_lookup(const HfstTransducer &transducer) {
+    int type = static_cast<int>(transducer.get_type());
+    return type == HFST_OL_TYPE || type == HFST_OLW_TYPE;
+}
+
+// A copy of `s`, allocated with malloc, for the caller to free with
+// hfst_free.
+char *copy_string(const std::string &s) {
+    char *copy = static_cast<char *>(std::malloc(s.size() + 1));
+    if (copy == nullptr) {
+        throw std::bad_alloc();
+    }
+    std::memcpy(copy, s.c_str(), s.size() + 1);
+    return copy;
+}
+
+char **copy_strings(const std::vector<std::string> &strings) {
+    // never allocate 0 bytes, as malloc may give NULL for that
+    size_t n = std::max<size_t>(strings.size(), 1);
+    char **copy = static_cast<char **>(std::calloc(n, sizeof(char *)));
+    if (copy == nullptr) {
+        throw std::bad_alloc();
+    }
+    try {
+        for (size_t i = 0; i < strings.size(); ++i) {
+            copy[i] = copy_string(strings[i]);
+        }
+    } catch (...) {
+        for (size_t i = 0; i < n; ++i) {
+            std::free(copy[i]);
+        }
+        std::free(copy);
+        throw;
+    }
+    return copy;
+}
+
+// Epsilons are written as empty strings to the callers.
+std::string without_epsilon(const std::string &symbol) {
+    return symbol == EPSILON ? std::string() : symbol;
+}
+
+// A temporary file, that is removed again when this goes out of scope.
+class TempFile {
+  public:
+    TempFile() {
+        const char *dir = std::getenv("TMPDIR");
+        std::string path = std::string(dir != nullptr && *dir != '\0' ? dir : "/tmp") +
+                           "/hfst-shim-XXXXXX";
+        std::vector<char> buffer(path.begin(), path.end());
+        buffer.push_back('\0');
+        int fd = mkstemp(buffer.data());
+        if (fd < 0) {
+            throw Error{OTHER};
+        }
+        close(fd);
+        path_ = buffer.data();
+    }
+
+    TempFile(const TempFile &) = delete;
+    TempFile &operator=(const TempFile &) = delete;
+
+    ~TempFile() {
+        if (!path_.empty()) {
+            unlink(path_.c_str());
+        }
+    }
+
+    const std::string &path() const { return path_; }
+
+    // Keep the file after this goes out of scope, and give its path.
+    std::string release() {
+        std::string path;
+        std::swap(path, path_);
+        return path;
+    }
+
+    void write(const char *data, size_t len) const {
+        FILE *file = std::fopen(path_.c_str(), "wb");
+        if (file == nullptr) {
+            throw Error{OTHER};
+        }
+        size_t written = len == 0 ? 0 : std::fwrite(data, 1, len, file);
+        bool ok = std::fclose(file) == 0;
+        if (!ok || written != len) {
+            throw Error{OTHER};
+        }
+    }
+
+  private:
+    std::string path_;
+};
+
+struct InputStream {
+    HfstInputStream *stream;
+    // The temporary file that the stream reads, if it was made from memory.
+    // It is removed when the stream is closed.
+    std::string temp_path;
+};
+
+InputStream *as_input_stream(const void *input_stream) {
+    return static_cast<InputStream *>(const_cast<void *>(input_stream));
+}
+
+// Split `s` into UTF-8 characters. Bytes that do not start a character are
+// taken on their own.
+std::vector<std::string> utf8_characters(const std::string &s) {
+    std::vector<std::string> characters;
+    size_t i = 0;
+    while (i < s.size()) {
+        unsigned char c = static_cast<unsigned char>(s[i]);
+        size_t len = 1;
+        if ((c >> 5) == 0x6) {
+            len = 2;
+        } else if ((c >> 4) == 0xe) {
+            len = 3;
+        } else if ((c >> 3) == 0x1e) {
+            len = 4;
+        }
+        len = std::min(len, s.size() - i);
+        characters.push_back(s.substr(i, len));
+        i += len;
+    }
+    return characters;
+}
+
+// ---------------------------------------------------------------------------
+// Paths
+
+struct Path {
+    std::string input;
+    std::string output;
+    float weight;
+};
+
+struct PathsIterator {
+    std::vector<Path> paths;
+    size_t position = 0;
+};
+
+PathsIterator *make_paths_iterator(const hfst::HfstTwoLevelPaths &paths) {
+    std::unique_ptr<PathsIterator> it(new PathsIterator());
+    for (const auto &two_level_path : paths) {
+        Path path{"", "", two_level_path.first};
+        for (const auto &pair : two_level_path.second) {
+            path.input += without_epsilon(pair.first);
+            path.output += without_epsilon(pair.second);
+        }
+        it->paths.push_back(path);
+    }
+    return it.release();
+}
+
+// ---------------------------------------------------------------------------
+// Weights
+
+// The lightest weights of getting from the initial state to each state
+// (`forward`), or from each state to a final state (`!forward`). States that
+// can not be reached, or that can not reach a final state, are left at
+// infinity. Weights are never negative in practice, but this does not rely on
+// it, and finds the shortest distances with repeated relaxation.
+std::vector<float> shortest_distances(const HfstBasicTransducer &basic, bool forward) {
+    const float infinity = std::numeric_limits<float>::infinity();
+    size_t n_states = basic.get_max_state() + 1;
+    std::vector<std::vector<std::pair<unsigned int, float>>> arcs(n_states);
+    for (unsigned int s = 0; s < n_states; ++s) {
+        for (const HfstBasicTransition &arc : basic.transitions(s)) {
+            if (forward) {
+                arcs[s].emplace_back(arc.get_target_state(), arc.get_weight());
+            } else {
+                arcs[arc.get_target_state()].emplace_back(s, arc.get_weight());
+            }
+        }
+    }
+
+    std::vector<float> distance(n_states, infinity);
+    std::deque<unsigned int> queue;
+    std::vector<bool> queued(n_states, false);
+    for (unsigned int s = 0; s < n_states; ++s) {
+        bool start = forward ? s == 0 : basic.is_final_state(s);
+        if (start) {
+            distance[s] = forward ? 0.0f : basic.get_final_weight(s);
+            queue.push_back(s);
+            queued[s] = true;
+        }
+    }
+    // a bound on the number of relaxations, so that negative cycles end
+    size_t budget = n_states * (n_states + 1) + 1;
+    while (!queue.empty() && budget > 0) {
+        unsigned int s = queue.front();
+        queue.pop_front();
+        queued[s] = false;
+        for (const auto &arc : arcs[s]) {
+            --budget;
+            float d = distance[s] + arc.second;
+            if (d < distance[arc.first]) {
+                distance[arc.first] = d;
+                if (!queued[arc.first]) {
+                    queue.push_back(arc.first);
+                    queued[arc.first] = true;
+                }
+            }
+        }
+    }
+    return distance;
+}
+
+thread_local float transform_scale = 1.0f;
+thread_local float transform_offset = 0.0f;
+
+float transform_weight(float weight) {
+    return weight * transform_scale + transform_offset;
+}
+
+// libhfst's settings, such as whether weights are encoded when
+// determinizing, are global. This is held while one is changed from its
+// default, and by the operations that read it.
+std::mutex settings_mutex;
+
+bool has_arc_weights(const HfstBasicTransducer &basic) {
+    for (unsigned int s = 0; s <= basic.get_max_state(); ++s) {
+        for (const HfstBasicTransition &arc : basic.transitions(s)) {
+            if (arc.get_weight() != 0.0f) {
+                return true;
+            }
+        }
+    }
+    return false;
+}
+
+// Does `basic` accept anything at all?
+bool accepts_anything(const HfstBasicTransducer &basic) {
+    std::vector<bool> seen(basic.get_max_state() + 1, false);
+    std::vector<unsigned int> stack{0};
+    seen[0] = true;
+    while (!stack.empty()) {
+        unsigned int s = stack.back();
+        stack.pop_back();
+        if (basic.is_final_state(s)) {
+            return true;
+        }
+        for (const HfstBasicTransition &arc : basic.transitions(s)) {
+            if (!seen[arc.get_target_state()]) {
+                seen[arc.get_target_state()] = true;
+                stack.push_back(arc.get_target_state());
+            }
+        }
+    }
+    return false;
+}
+
+// ---------------------------------------------------------------------------
+// Lookup
+
+struct TraceArc {
+    unsigned int source;
+    unsigned int target;
+    std::string input;
+    std::string output;
+    float weight;
+};
+
+struct LookupResult {
+    std::vector<std::string> symbols;
+    float weight;
+    // Empty, unless the lookup was made with `alignment`.
+    std::vector<std::pair<std::string, std::string>> alignment;
+    // Empty, unless the lookup was made with `trace`.
+    std::vector<TraceArc> trace;
+};
+
+struct Lookup {
+    std::vector<LookupResult> results;
+    bool alignment = false;
+    bool trace = false;
+    bool timed_out = false;
+    bool infinitely_ambiguous = false;
+};
+
+HfstLookupOptions default_options() {
+    HfstLookupOptions options;
+    options.max_results = -1;
+    options.time_cutoff = 0.0;
+    options.obey_flags = true;
+    options.check_infinitely_ambiguous = false;
+    options.alignment = false;
+    options.max_weight = std::numeric_limits<float>::infinity();
+    options.trace = false;
+    options.unknown_symbols = UNKNOWN_SYMBOLS_FAIL;
+    return options;
+}
+
+bool is_special(const std::string &symbol) {
+    return symbol.size() >= 4 && symbol.compare(0, 2, "@_") == 0 &&
+           symbol.compare(symbol.size() - 2, 2, "_@") == 0;
+}
+
+// Split `s` into symbols the same way as the hfst crate does: At each
+// position, the longest symbol of `alphabet` that matches is taken, or else
+// the character on its own. The special symbols are never typed.
+std::vector<std::string> tokenize(const std::string &s, const hfst::StringSet &alphabet) {
+    size_t longest = 0;
+    for (const std::string &symbol : alphabet) {
+        if (!is_special(symbol)) {
+            longest = std::max(longest, symbol.size());
+        }
+    }
+    std::vector<std::string> tokens;
+    size_t i = 0;
+    while (i < s.size()) {
+        size_t character = utf8_characters(s.substr(i, 4)).front().size();
+        size_t len = std::min(longest, s.size() - i);
+        for (; len > character; --len) {
+            std::string candidate = s.substr(i, len);
+            if (!is_special(candidate) && alphabet.count(candidate) != 0) {
+                break;
+            }
+        }
+        len = std::max(len, character);
+        tokens.push_back(s.substr(i, len));
+        i += len;
+    }
+    return tokens;
+}
+
+// A flag diacritic, such as @P.CASE.NOM@, split up.
+struct Flag {
+    char op;
+    std::string feature;
+    std::string value;
+};
+
+bool parse_flag(const std::string &symbol, Flag &flag) {
+    if (symbol.size() < 5 || symbol.front() != '@' || symbol.back() != '@' || symbol[2] != '.') {
+        return false;
+    }
+    if (std::string("PNRDCU").find(symbol[1]) == std::string::npos) {
+        return false;
+    }
+    std::string rest = symbol.substr(3, symbol.size() - 4);
+    size_t dot = rest.find('.');
+    flag.op = symbol[1];
+    flag.feature = rest.substr(0, dot);
+    flag.value = dot == std::string::npos ? std::string() : rest.substr(dot + 1);
+    return !flag.feature.empty() && rest.find('@') == std::string::npos;
+}
+
+// The values that the flag diacritics on a path have set so far.
+class FlagState {
+  public:
+    // Apply the flag, and tell whether the path may go on through it.
+    bool apply(const Flag &flag) {
+        auto it = features_.find(flag.feature);
+        bool is_set = it != features_.end();
+        switch (flag.op) {
+        case 'P':
+            features_[flag.feature] = Value{flag.value, true};
+            return true;
+        case 'N':
+            features_[flag.feature] = Value{flag.value, false};
+            return true;
+        case 'C':
+            features_.erase(flag.feature);
+            return true;
+        case 'R':
+            if (flag.value.empty()) {
+                return is_set;
+            }
+            return is_set && equals(it->second, flag.value);
+        case 'D':
+            if (flag.value.empty()) {
+                return !is_set;
+            }
+            return !is_set || !equals(it->second, flag.value);
+        case 'U':
+            if (is_set && !equals(it->second, flag.value)) {
+                return false;
+            }
+            features_[flag.feature] = Value{flag.value, true};
+            return true;
+        }
+        return false;
+    }
+
+  private:
+    struct Value {
+        std::string value;
+        bool positive;
+    };
+
+    static bool equals(const Value &value, const std::string &other) {
+        return (value.value == other) == value.positive;
+    }
+
+    std::map<std::string, Value> features_;
+};
+
+// The lookup for what optimized-lookup can not do: A depth-first search over
+// the arcs of a basic transducer, that keeps track of the path it is on.
+class Search {
+  public:
+    Search(const HfstBasicTransducer &transducer,
+           const std::vector<std::string> &input,
+           const HfstLookupOptions &options,
+           Lookup &lookup)
+        : transducer_(transducer), input_(input), options_(options), lookup_(lookup),
+          start_(std::chrono::steady_clock::now()) {
+        const auto &alphabet = transducer.get_alphabet();
+        for (const std::string &token : input) {
+            known_.push_back(alphabet.count(token) != 0);
+        }
+    }
+
+    void run() {
+        if (options_.max_results != 0) {
+            visit(0, 0, 0.0f);
+        }
+        if (lookup_.infinitely_ambiguous) {
+            lookup_.results.clear();
+        }
+    }
+
+  private:
+    struct Step {
+        std::string input;
+        std::string output;
+        unsigned int source;
+        const HfstBasicTransition *arc;
+    };
+
+    void visit(unsigned int state, size_t position, float weight) {
+        if (stopped_ || weight > options_.max_weight) {
+            return;
+        }
+        if (++steps_ % 1024 == 0 && out_of_time()) {
+            lookup_.timed_out = true;
+            stopped_ = true;
+            return;
+        }
+        auto configuration = std::make_pair(state, position);
+        if (!on_path_.insert(configuration).second) {
+            // a cycle that consumes no input: following it would give
+            // infinitely many paths
+            if (options_.check_infinitely_ambiguous) {
+                lookup_.infinitely_ambiguous = true;
+                stopped_ = true;
+            }
+            return;
+        }
+        if (position == input_.size() && transducer_.is_final_state(state)) {
+            add_result(weight + transducer_.get_final_weight(state));
+        }
+        for (const HfstBasicTransition &arc : transducer_.transitions(state)) {
+            if (stopped_) {
+                break;
+            }
+            follow(state, position, weight, arc);
+        }
+        on_path_.erase(configuration);
+    }
+
+    void follow(unsigned int state, size_t position, float weight, const HfstBasicTransition &arc) {
+        const std::string &input = arc.get_input_symbol();
+        const std::string &output = arc.get_output_symbol();
+        float next_weight = weight + arc.get_weight();
+
+        Flag flag;
+        if (parse_flag(input, flag)) {
+            // flag diacritics consume no input, and are kept in the output
+            FlagState saved = flags_;
+            if (!options_.obey_flags || flags_.apply(flag)) {
+                take(Step{input, output, state, &arc}, position, next_weight);
+            }
+            flags_ = saved;
+            return;
+        }
+        if (input == EPSILON) {
+            take(Step{"", output, state, &arc}, position, next_weight);
+            return;
+        }
+        if (position >= input_.size()) {
+            return;
+        }
+        const std::string &token = input_[position];
+        if (input == token) {
+            take(Step{token, output, state, &arc}, position + 1, next_weight);
+            return;
+        }
+        if (known_[position] || options_.unknown_symbols != UNKNOWN_SYMBOLS_IDENTITY) {
+            return;
+        }
+        if (input == IDENTITY) {
+            take(Step{token, token, state, &arc}, position + 1, next_weight);
+        } else if (input == UNKNOWN && output != UNKNOWN) {
+            take(Step{token, output, state, &arc}, position + 1, next_weight);
+        }
+    }
+
+    void take(const Step &step, size_t position, float weight) {
+        path_.push_back(step);
+        visit(step.arc->get_target_state(), position, weight);
+        path_.pop_back();
+    }
+
+    void add_result(float weight) {
+        if (weight > options_.max_weight) {
+            return;
+        }
+        LookupResult result;
+        result.weight = weight;
+        for (const Step &step : path_) {
+            if (step.output != EPSILON) {
+                result.symbols.push_back(step.output);
+            }
+            if (options_.alignment) {
+                result.alignment.emplace_back(without_epsilon(step.input),
+                                              without_epsilon(step.output));
+            }
+            if (options_.trace) {
+                result.trace.push_back(TraceArc{step.source,
+                                                step.arc->get_target_state(),
+                                                without_epsilon(step.arc->get_input_symbol()),
+                                                without_epsilon(step.arc->get_output_symbol()),
+                                                step.arc->get_weight()});
+            }
+        }
+        lookup_.results.push_back(std::move(result));
+        if (options_.max_results > 0 &&
+            lookup_.results.size() >= static_cast<size_t>(options_.max_results)) {
+            stopped_ = true;
+        }
+    }
+
+    bool out_of_time() const {
+        if (options_.time_cutoff <= 0.0) {
+            return false;
+        }
+        std::chrono::duration<double> elapsed = std::chrono::steady_clock::now() - start_;
+        return elapsed.count() >= options_.time_cutoff;
+    }
+
+    const HfstBasicTransducer &transducer_;
+    const std::vector<std::string> &input_;
+    const HfstLookupOptions &options_;
+    Lookup &lookup_;
+    std::chrono::steady_clock::time_point start_;
+    // Is the token at each position of the input in the alphabet?
+    std::vector<bool> known_;
+    std::set<std::pair<unsigned int, size_t>> on_path_;
+    std::vector<Step> path_;
+    FlagState flags_;
+    size_t steps_ = 0;
+    bool stopped_ = false;
+};
+
+// Look up with libhfst's optimized lookup. `text` is the input as a string,
+// which libhfst tokenizes by itself, or NULL to look up `tokens` as they are.
+void optimized_lookup(HfstTransducer &transducer,
+                      const std::string *text,
+                      const std::vector<std::string> &tokens,
+                      const HfstLookupOptions &options,
+                      Lookup &lookup) {
+    if (options.max_results == 0) {
+        return;
+    }
+    if (options.check_infinitely_ambiguous) {
+        bool infinite = text != nullptr ? transducer.is_lookup_infinitely_ambiguous(*text)
+                                        : transducer.is_lookup_infinitely_ambiguous(tokens);
+        if (infinite) {
+            lookup.infinitely_ambiguous = true;
+            return;
+        }
+    }
+    auto start = std::chrono::steady_clock::now();
+    std::unique_ptr<hfst::HfstOneLevelPaths> paths(
+        text != nullptr
+            ? transducer.lookup_fd(*text, options.max_results, options.time_cutoff)
+            : transducer.lookup_fd(tokens, options.max_results, options.time_cutoff));
+    std::chrono::duration<double> elapsed = std::chrono::steady_clock::now() - start;
+    lookup.timed_out = options.time_cutoff > 0.0 && elapsed.count() >= options.time_cutoff;
+    for (const auto &path : *paths) {
+        if (path.first > options.max_weight) {
+            continue;
+        }
+        LookupResult result;
+        result.weight = path.first;
+        for (const std::string &symbol : path.second) {
+            if (symbol != EPSILON) {
+                result.symbols.push_back(symbol);
+            }
+        }
+        lookup.results.push_back(std::move(result));
+    }
+}
+
+// Look up `text`, or if it is NULL, `tokens`, in an optimized-lookup
+// transducer.
+Lookup *lookup_in(HfstTransducer &transducer,
+                  const std::string *text,
+                  std::vector<std::string> tokens,
+                  const HfstLookupOptions &options) {
+    if (!is_optimized_lookup(transducer)) {
+        throw Error{NOT_OPTIMIZED_FOR_LOOKUP};
+    }
+    std::unique_ptr<Lookup> lookup(new Lookup());
+    lookup->alignment = options.alignment;
+    lookup->trace = options.trace;
+
+    hfst::StringSet alphabet = transducer.get_alphabet();
+    if (text != nullptr) {
+        tokens = tokenize(*text, alphabet);
+    }
+    bool all_known = std::all_of(tokens.begin(), tokens.end(), [&](const std::string &token) {
+        return alphabet.count(token) != 0;
+    });
+    if (!all_known && options.unknown_symbols == UNKNOWN_SYMBOLS_FAIL) {
+        return lookup.release();
+    }
+
+    if (options.alignment || options.trace || !options.obey_flags) {
+        HfstBasicTransducer basic(transducer);
+        Search(basic, tokens, options, *lookup).run();
+    } else {
+        // with identity, let libhfst decide what the unknown symbols match
+        optimized_lookup(transducer, all_known ? nullptr : text, tokens, options, *lookup);
+    }
+    return lookup.release();
+}
+
+const Lookup *as_lookup(const void *lookup) {
+    return static_cast<const Lookup *>(lookup);
+}
+
+} // namespace hfst_shim
+
+using namespace hfst_shim;
+
+struct ResultIterator {
+    const Lookup *lookup;
+    size_t position;
+};
+
+namespace {
+
+const LookupResult &current(const ResultIterator *it) {
+    return it->lookup->results[it->position];
+}
+
+} // namespace
+
+extern "C" {
+
+void hfst_free(void *ptr) {
+    std::free(ptr);
+}
+
+// ---------------------------------------------------------------------------
+// Input streams
+
+void *hfst_input_stream(const char *path, int *err) {
+    InputStream *result = nullptr;
+    guard(err, [&] {
+        std::unique_ptr<HfstInputStream> stream(new HfstInputStream(path));
+        result = new InputStream{stream.release(), ""};
+    });
+    if (result == nullptr) {
+        // libhfst tells files that can not be opened apart from files that
+        // are not transducers with the same exception anyway
+        set_err(err, NOT_TRANSDUCER_STREAM);
+    }
+    return result;
+}
+
+void *hfst_input_stream_from_memory(const char *data, size_t len) {
+    InputStream *result = nullptr;
+    int err = 0;
+    guard(&err, [&] {
+        TempFile file;
+        file.write(data, len);
+        std::unique_ptr<HfstInputStream> stream(new HfstInputStream(file.path()));
+        result = new InputStream{stream.release(), file.release()};
+    });
+    return result;
+}
+
+void hfst_input_stream_close(const void *input_stream) {
+    InputStream *stream = as_input_stream(input_stream);
+    int err = 0;
+    guard(&err, [&] { stream->stream->close(); });
+    delete stream->stream;
+    if (!stream->temp_path.empty()) {
+        unlink(stream->temp_path.c_str());
+    }
+    delete stream;
+}
+
+bool hfst_input_stream_is_eof(const void *input_stream) {
+    bool result = true;
+    int err = 0;
+    guard(&err, [&] { result = as_input_stream(input_stream)->stream->is_eof(); });
+    return result;
+}
+
+bool hfst_input_stream_is_bad(const void *input_stream) {
+    bool result = true;
+    int err = 0;
+    guard(&err, [&] { result = as_input_stream(input_stream)->stream->is_bad(); });
+    return result;
+}
+
+int hfst_input_stream_type(const void *input_stream) {
+    int result = ERROR_TYPE;
+    int err = 0;
+    guard(&err, [&] {
+        result = static_cast<int>(as_input_stream(input_stream)->stream->get_type());
+    });
+    return result;
+}
+
+void *hfst_transducer_from_stream(const void *input_stream) {
+    HfstTransducer *result = nullptr;
+    int err = 0;
+    guard(&err, [&] { result = new HfstTransducer(*as_input_stream(input_stream)->stream); });
+    return result;
+}
+
+// ---------------------------------------------------------------------------
+// Output streams
+
+void *hfst_output_stream(const char *path, int implementation_type, int *err) {
+    HfstOutputStream *result = nullptr;
+    guard(err, [&] { result = new HfstOutputStream(path, as_type(implementation_type), true); });
+    return result;
+}
+
+void hfst_output_stream_write(void *output_stream, const void *transducer, int *err) {
+    guard(err, [&] {
+        HfstTransducer copy(*as_transducer(transducer));
+        *static_cast<HfstOutputStream *>(output_stream) << copy;
+    });
+}
+
+void hfst_output_stream_close(void *output_stream, int *err) {
+    guard(err, [&] { static_cast<HfstOutputStream *>(output_stream)->close(); });
+}
+
+void hfst_output_stream_free(void *output_stream) {
+    int err = 0;
+    guard(&err, [&] { delete static_cast<HfstOutputStream *>(output_stream); });
+}
+
+// ---------------------------------------------------------------------------
+// Transducers
+
+void *hfst_empty_transducer(void) {
+    HfstTransducer *result = nullptr;
+    int err = 0;
+    guard(&err, [&] { result = new HfstTransducer(as_type(TROPICAL_OPENFST_TYPE)); });
+    return result;
+}
+
+void *hfst_transducer_copy(const void *transducer) {
+    HfstTransducer *result = nullptr;
+    int err = 0;
+    guard(&err, [&] { result = new HfstTransducer(*as_transducer(transducer)); });
+    return result;
+}
+
+void hfst_transducer_free(void *transducer) {
+    int err = 0;
+    guard(&err, [&] { delete as_transducer(transducer); });
+}
+
+int hfst_transducer_type(const void *transducer) {
+    return static_cast<int>(as_transducer(transducer)->get_type());
+}
+
+void *hfst_transducer_from_string_pairs(const char *const *inputs,
+                                        const char *const *outputs,
+                                        const float *weights,
+                                        size_t n_pairs,
+                                        int implementation_type,
+                                        int *err) {
+    HfstTransducer *result = nullptr;
+    guard(err, [&] {
+        HfstBasicTransducer basic;
+        for (size_t i = 0; i < n_pairs; ++i) {
+            std::vector<std::string> input = utf8_characters(inputs[i]);
+            std::vector<std::string> output = utf8_characters(outputs[i]);
+            // an empty pair still needs an arc, to give it a path of its own
+            size_t len = std::max<size_t>({input.size(), output.size(), 1});
+            unsigned int state = 0;
+            for (size_t j = 0; j < len; ++j) {
+                unsigned int next = basic.add_state();
+                basic.add_transition(state,
+                                     HfstBasicTransition(next,
+                                                         j < input.size() ? input[j] : EPSILON,
+                                                         j < output.size() ? output[j] : EPSILON,
+                                                         0.0f));
+                state = next;
+            }
+            basic.set_final_weight(state, weights[i]);
+        }
+        result = new HfstTransducer(basic, as_type(implementation_type));
+    });
+    return result;
+}
+
+void *hfst_transducer_from_att(const char *text,
+                               size_t len,
+                               const char *epsilon,
+                               int implementation_type,
+                               int *err) {
+    HfstTransducer *result = nullptr;
+    guard(err, [&] {
+        TempFile file;
+        file.write(text, len);
+        FILE *f = std::fopen(file.path().c_str(), "rb");
+        if (f == nullptr) {
+            throw Error{OTHER};
+        }
+        unsigned int linecount = 0;
+        try {
+            result = new HfstTransducer(f, as_type(implementation_type), epsilon, linecount);
+        } catch (...) {
+            std::fclose(f);
+            throw;
+        }
+        std::fclose(f);
+    });
+    return result;
+}
+
+char *hfst_transducer_to_att(const void *transducer, int *err) {
+    char *result = nullptr;
+    guard(err, [&] {
+        char *buffer = nullptr;
+        size_t size = 0;
+        FILE *f = open_memstream(&buffer, &size);
+        if (f == nullptr) {
+            throw Error{OTHER};
+        }
+        try {
+            as_transducer(transducer)->write_in_att_format(f, true);
+        } catch (...) {
+            std::fclose(f);
+            std::free(buffer);
+            throw;
+        }
+        std::fclose(f);
+        result = buffer;
+    });
+    return result;
+}
+
+void *hfst_xre_compile(const char *regex, int implementation_type, char **error_message, int *err) {
+    HfstTransducer *result = nullptr;
+    guard(err, [&] {
+        hfst::xre::XreCompiler compiler(as_type(implementation_type));
+        std::ostringstream errors;
+        compiler.set_error_stream(&errors);
+        result = compiler.compile(regex);
+        if (result == nullptr) {
+            std::string message = errors.str();
+            while (!message.empty() && (message.back() == '\n' || message.back() == ' ')) {
+                message.pop_back();
+            }
+            if (message.empty()) {
+                message = "invalid regular expression";
+            }
+            if (error_message != nullptr) {
+                *error_message = copy_string(message);
+            }
+            throw Error{XRE_COMPILE_ERROR};
+        }
+    });
+    return result;
+}
+
+void **hfst_twolc_compile(const char *text,
+                          size_t len,
+                          int implementation_type,
+                          size_t *n_rules,
+                          char **error_message,
+                          int *err) {
+    void **result = nullptr;
+    *n_rules = 0;
+    guard(err, [&] {
+        TempFile input;
+        input.write(text, len);
+        TempFile output;
+        int status = hfst::twolc::TwolcCompiler::compile(
+            input.path(), output.path(), true, false, false, true, as_type(implementation_type));
+        if (status != 0) {
+            if (error_message != nullptr) {
+                *error_message = copy_string("the twolc rules do not compile, see stderr");
+            }
+            throw Error{TWOLC_COMPILE_ERROR};
+        }
+
+        std::vector<std::unique_ptr<HfstTransducer>> rules;
+        HfstInputStream in(output.path());
+        while (!in.is_eof()) {
+            rules.emplace_back(new HfstTransducer(in));
+        }
+        in.close();
+
+        size_t n = std::max<size_t>(rules.size(), 1);
+        void **array = static_cast<void **>(std::calloc(n, sizeof(void *)));
+        if (array == nullptr) {
+            throw std::bad_alloc();
+        }
+        for (size_t i = 0; i < rules.size(); ++i) {
+            array[i] = rules[i].release();
+        }
+        *n_rules = rules.size();
+        result = array;
+    });
+    return result;
+}
+
+// ---------------------------------------------------------------------------
+// Operations
+
+void hfst_compose(void *transducer, const void *other, bool harmonize, int *err) {
+    guard(err, [&] { as_transducer(transducer)->compose(*as_transducer(other), harmonize); });
+}
+
+void hfst_disjunct(void *transducer, const void *other, bool harmonize, int *err) {
+    guard(err, [&] { as_transducer(transducer)->disjunct(*as_transducer(other), harmonize); });
+}
+
+void hfst_intersect(void *transducer, const void *other, bool harmonize, int *err) {
+    guard(err, [&] { as_transducer(transducer)->intersect(*as_transducer(other), harmonize); });
+}
+
+void hfst_concatenate(void *transducer, const void *other, bool harmonize, int *err) {
+    guard(err, [&] { as_transducer(transducer)->concatenate(*as_transducer(other), harmonize); });
+}
+
+void hfst_subtract(void *transducer, const void *other, bool harmonize, int *err) {
+    guard(err, [&] { as_transducer(transducer)->subtract(*as_transducer(other), harmonize); });
+}
+
+void hfst_minimize(void *transducer, int *err) {
+    guard(err, [&] {
+        std::lock_guard<std::mutex> lock(settings_mutex);
+        as_transducer(transducer)->minimize();
+    });
+}
+
+void hfst_determinize(void *transducer, bool weighted, int *err) {
+    guard(err, [&] {
+        HfstTransducer *t = as_transducer(transducer);
+        std::lock_guard<std::mutex> lock(settings_mutex);
+        if (weighted) {
+            HfstBasicTransducer basic(*t);
+            if (has_arc_weights(basic) && t->is_cyclic()) {
+                throw Error{NOT_DETERMINIZABLE};
+            }
+            t->determinize();
+            return;
+        }
+        // weights are not encoded by default, and nothing else changes that
+        hfst::set_encode_weights(true);
+        try {
+            t->determinize();
+        } catch (...) {
+            hfst::set_encode_weights(false);
+            throw;
+        }
+        hfst::set_encode_weights(false);
+    });
+}
+
+void hfst_remove_epsilons(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->remove_epsilons(); });
+}
+
+void hfst_invert(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->invert(); });
+}
+
+void hfst_reverse(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->reverse(); });
+}
+
+void hfst_input_project(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->input_project(); });
+}
+
+void hfst_output_project(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->output_project(); });
+}
+
+void hfst_repeat_star(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->repeat_star(); });
+}
+
+void hfst_repeat_plus(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->repeat_plus(); });
+}
+
+void hfst_repeat_n(void *transducer, unsigned int n, int *err) {
+    guard(err, [&] { as_transducer(transducer)->repeat_n(n); });
+}
+
+void hfst_repeat_n_to_k(void *transducer, unsigned int n, unsigned int k, int *err) {
+    guard(err, [&] { as_transducer(transducer)->repeat_n_to_k(n, k); });
+}
+
+void hfst_optionalize(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->optionalize(); });
+}
+
+void hfst_n_best(void *transducer, unsigned int n, int *err) {
+    guard(err, [&] { as_transducer(transducer)->n_best(n); });
+}
+
+void hfst_push_weights(void *transducer, unsigned int push_type, int *err) {
+    guard(err, [&] {
+        if (push_type != TO_INITIAL_STATE && push_type != TO_FINAL_STATE) {
+            throw Error{OTHER};
+        }
+        as_transducer(transducer)->push_weights(static_cast<hfst::PushType>(push_type));
+    });
+}
+
+void hfst_prune_weights(void *transducer, float max_weight, int *err) {
+    guard(err, [&] {
+        HfstTransducer *t = as_transducer(transducer);
+        HfstBasicTransducer basic(*t);
+        std::vector<float> forward = shortest_distances(basic, true);
+        std::vector<float> backward = shortest_distances(basic, false);
+
+        HfstBasicTransducer pruned;
+        pruned.add_symbols_to_alphabet(basic.get_alphabet());
+        for (unsigned int s = 0; s <= basic.get_max_state(); ++s) {
+            pruned.add_state(s);
+            for (const HfstBasicTransition &arc : basic.transitions(s)) {
+                unsigned int target = arc.get_target_state();
+                if (forward[s] + arc.get_weight() + backward[target] <= max_weight) {
+                    pruned.add_state(target);
+                    pruned.add_transition(s, arc);
+                }
+            }
+            if (basic.is_final_state(s) && forward[s] + basic.get_final_weight(s) <= max_weight) {
+                pruned.set_final_weight(s, basic.get_final_weight(s));
+            }
+        }
+
+        HfstTransducer result(pruned, t->get_type());
+        result.prune();
+        result.set_name(t->get_name());
+        for (const auto &property : t->get_properties()) {
+            result.set_property(property.first, property.second);
+        }
+        *t = result;
+    });
+}
+
+void hfst_insert_freely(void *transducer,
+                        const char *input,
+                        const char *output,
+                        bool harmonize,
+                        int *err) {
+    guard(err, [&] {
+        auto pair = std::make_pair(std::string(input), std::string(output));
+        as_transducer(transducer)->insert_freely(pair, harmonize);
+    });
+}
+
+void hfst_insert_freely_transducer(void *transducer, const void *other, bool harmonize, int *err) {
+    guard(err, [&] { as_transducer(transducer)->insert_freely(*as_transducer(other), harmonize); });
+}
+
+void hfst_compose_intersect(void *transducer, const void *const *rules, size_t n_rules, int *err) {
+    guard(err, [&] {
+        hfst::HfstTransducerVector copies;
+        for (size_t i = 0; i < n_rules; ++i) {
+            copies.push_back(*as_transducer(rules[i]));
+        }
+        as_transducer(transducer)->compose_intersect(copies);
+    });
+}
+
+void hfst_eliminate_flags(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->eliminate_flags(); });
+}
+
+void hfst_eliminate_flag(void *transducer, const char *name, int *err) {
+    guard(err, [&] { as_transducer(transducer)->eliminate_flag(name); });
+}
+
+void hfst_convert(void *transducer, int implementation_type, int *err) {
+    guard(err, [&] { as_transducer(transducer)->convert(as_type(implementation_type)); });
+}
+
+void hfst_harmonize(void *transducer, void *other, int *err) {
+    guard(err, [&] { as_transducer(transducer)->harmonize(*as_transducer(other)); });
+}
+
+void hfst_set_final_weights(void *transducer, float weight, bool increment, int *err) {
+    guard(err, [&] { as_transducer(transducer)->set_final_weights(weight, increment); });
+}
+
+void hfst_transform_weights(void *transducer, float scale, float offset, int *err) {
+    guard(err, [&] {
+        // libhfst takes a plain function pointer, so the parameters go through
+        // thread locals
+        transform_scale = scale;
+        transform_offset = offset;
+        as_transducer(transducer)->transform_weights(&transform_weight);
+    });
+}
+
+void hfst_prune_alphabet(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->prune_alphabet(); });
+}
+
+void hfst_prune(void *transducer, int *err) {
+    guard(err, [&] { as_transducer(transducer)->prune(); });
+}
+
+// ---------------------------------------------------------------------------
+// Queries
+
+bool hfst_is_cyclic(const void *transducer) {
+    bool result = false;
+    int err = 0;
+    guard(&err, [&] { result = as_transducer(transducer)->is_cyclic(); });
+    return result;
+}
+
+void hfst_transducer_stats(const void *transducer, HfstTransducerStats *stats, int *err) {
+    guard(err, [&] {
+        const HfstTransducer *t = as_transducer(transducer);
+        HfstBasicTransducer basic(*t);
+        HfstTransducerStats result{};
+        result.is_deterministic = true;
+        result.n_states = basic.get_max_state() + 1;
+        for (unsigned int s = 0; s <= basic.get_max_state(); ++s) {
+            if (basic.is_final_state(s)) {
+                ++result.n_final_states;
+            }
+            std::set<std::pair<std::string, std::string>> labels;
+            for (const HfstBasicTransition &arc : basic.transitions(s)) {
+                ++result.n_arcs;
+                auto label = std::make_pair(arc.get_input_symbol(), arc.get_output_symbol());
+                bool epsilon = label.first == EPSILON && label.second == EPSILON;
+                if (epsilon || !labels.insert(label).second) {
+                    result.is_deterministic = false;
+                }
+            }
+        }
+
+        HfstTransducer minimized(basic, as_type(TROPICAL_OPENFST_TYPE));
+        {
+            std::lock_guard<std::mutex> lock(settings_mutex);
+            minimized.minimize();
+        }
+        HfstBasicTransducer minimized_basic(minimized);
+        result.is_minimal = minimized_basic.get_max_state() + 1 >= result.n_states;
+        result.implementation_type = static_cast<int>(t->get_type());
+        *stats = result;
+    });
+}
+
+bool hfst_is_subset(const void *transducer, const void *other, int *err) {
+    bool result = false;
+    guard(err, [&] {
+        HfstTransducer difference(*as_transducer(transducer));
+        difference.subtract(*as_transducer(other));
+        result = !accepts_anything(HfstBasicTransducer(difference));
+    });
+    return result;
+}
+
+bool hfst_compare(const void *transducer, const void *other, bool harmonize, int *err) {
+    bool result = false;
+    guard(err, [&] {
+        result = as_transducer(transducer)->compare(*as_transducer(other), harmonize);
+    });
+    return result;
+}
+
+char **hfst_alphabet(const void *transducer, size_t *n_symbols) {
+    char **result = nullptr;
+    *n_symbols = 0;
+    int err = 0;
+    guard(&err, [&] {
+        hfst::StringSet alphabet = as_transducer(transducer)->get_alphabet();
+        std::vector<std::string> symbols(alphabet.begin(), alphabet.end());
+        result = copy_strings(symbols);
+        *n_symbols = symbols.size();
+    });
+    return result;
+}
+
+char *hfst_get_name(const void *transducer) {
+    char *result = nullptr;
+    int err = 0;
+    guard(&err, [&] { result = copy_string(as_transducer(transducer)->get_name()); });
+    return result;
+}
+
+void hfst_set_name(void *transducer, const char *name) {
+    int err = 0;
+    guard(&err, [&] { as_transducer(transducer)->set_name(name); });
+}
+
+char *hfst_get_property(const void *transducer, const char *key) {
+    char *result = nullptr;
+    int err = 0;
+    guard(&err, [&] {
+        const auto &properties = as_transducer(transducer)->get_properties();
+        auto it = properties.find(key);
+        if (it != properties.end()) {
+            result = copy_string(it->second);
+        }
+    });
+    return result;
+}
+
+void hfst_set_property(void *transducer, const char *key, const char *value) {
+    int err = 0;
+    guard(&err, [&] { as_transducer(transducer)->set_property(key, value); });
+}
+
+char **hfst_property_keys(const void *transducer, size_t *n_keys) {
+    char **result = nullptr;
+    *n_keys = 0;
+    int err = 0;
+    guard(&err, [&] {
+        std::vector<std::string> keys;
+        for (const auto &property : as_transducer(transducer)->get_properties()) {
+            keys.push_back(property.first);
+        }
+        result = copy_strings(keys);
+        *n_keys = keys.size();
+    });
+    return result;
+}
+
+// ---------------------------------------------------------------------------
+// Paths
+
+void *hfst_extract_paths(const void *transducer, int max_num, int cycles, int *err) {
+    PathsIterator *result = nullptr;
+    guard(err, [&] {
+        const HfstTransducer *t = as_transducer(transducer);
+        if (cycles < 0 && t->is_cyclic()) {
+            throw Error{TRANSDUCER_IS_CYCLIC};
+        }
+        hfst::HfstTwoLevelPaths paths;
+        t->extract_paths(paths, max_num, cycles);
+        result = make_paths_iterator(paths);
+    });
+    return result;
+}
+
+void *hfst_extract_random_paths(const void *transducer, int max_num, bool obey_flags, int *err) {
+    PathsIterator *result = nullptr;
+    guard(err, [&] {
+        const HfstTransducer *t = as_transducer(transducer);
+        hfst::HfstTwoLevelPaths paths;
+        if (obey_flags) {
+            t->extract_random_paths_fd(paths, max_num, true);
+        } else {
+            t->extract_random_paths(paths, max_num);
+        }
+        result = make_paths_iterator(paths);
+    });
+    return result;
+}
+
+void *hfst_shortest_path(const void *transducer, int *err) {
+    PathsIterator *result = nullptr;
+    guard(err, [&] {
+        HfstTransducer best(*as_transducer(transducer));
+        best.n_best(1);
+        hfst::HfstTwoLevelPaths paths;
+        best.extract_paths(paths, -1, 0);
+        hfst::HfstTwoLevelPaths shortest;
+        if (!paths.empty()) {
+            shortest.insert(*paths.begin());
+        }
+        result = make_paths_iterator(shortest);
+    });
+    return result;
+}
+
+bool hfst_paths_iterator_done(void *it) {
+    PathsIterator *paths = static_cast<PathsIterator *>(it);
+    return paths->position >= paths->paths.size();
+}
+
+void hfst_paths_iterator_value(void *it, char **input, char **output, float *w) {
+    PathsIterator *paths = static_cast<PathsIterator *>(it);
+    Path &path = paths->paths[paths->position];
+    *input = &path.input[0];
+    *output = &path.output[0];
+    *w = path.weight;
+}
+
+void hfst_paths_iterator_next(void *it) {
+    ++static_cast<PathsIterator *>(it)->position;
+}
+
+void hfst_paths_iterator_free(void *it) {
+    delete static_cast<PathsIterator *>(it);
+}
+
+// ---------------------------------------------------------------------------
+// Basic transducers
+
+void *hfst_basic_transducer_new(void) {
+    HfstBasicTransducer *result = nullptr;
+    int err = 0;
+    guard(&err, [&] { result = new HfstBasicTransducer(); });
+    return result;
+}
+
+void hfst_basic_transducer_free(void *transducer) {
+    delete static_cast<HfstBasicTransducer *>(transducer);
+}
+
+unsigned int hfst_basic_transducer_add_state(void *transducer) {
+    unsigned int result = 0;
+    int err = 0;
+    guard(&err, [&] { result = static_cast<HfstBasicTransducer *>(transducer)->add_state(); });
+    return result;
+}
+
+void hfst_basic_transducer_add_transition(void *transducer,
+                                          unsigned int source,
+                                          unsigned int target,
+                                          const char *input,
+                                          const char *output,
+                                          float weight) {
+    int err = 0;
+    guard(&err, [&] {
+        HfstBasicTransducer *basic = static_cast<HfstBasicTransducer *>(transducer);
+        basic->add_state(source);
+        basic->add_state(target);
+        basic->add_transition(source, HfstBasicTransition(target, input, output, weight));
+    });
+}
+
+void hfst_basic_transducer_set_final_weight(void *transducer, unsigned int state, float weight) {
+    int err = 0;
+    guard(&err, [&] {
+        HfstBasicTransducer *basic = static_cast<HfstBasicTransducer *>(transducer);
+        basic->add_state(state);
+        basic->set_final_weight(state, weight);
+    });
+}
+
+void *hfst_basic_transducer_to_transducer(const void *transducer,
+                                          int implementation_type,
+                                          int *err) {
+    HfstTransducer *result = nullptr;
+    guard(err, [&] {
+        const HfstBasicTransducer *basic = static_cast<const HfstBasicTransducer *>(transducer);
+        result = new HfstTransducer(*basic, as_type(implementation_type));
+    });
+    return result;
+}
+
+// ---------------------------------------------------------------------------
+// Lookup
+
+void *hfst_lookup(void *transducer, const char *input, int *err) {
+    return hfst_lookup_with_options(transducer, input, nullptr, err);
+}
+
+void *hfst_lookup_with_options(void *transducer,
+                               const char *input,
+                               const HfstLookupOptions *options,
+                               int *err) {
+    Lookup *result = nullptr;
+    guard(err, [&] {
+        std::string text(input);
+        result = lookup_in(*as_transducer(transducer),
+                           &text,
+                           {},
+                           options != nullptr ? *options : default_options());
+    });
+    return result;
+}
+
+void *hfst_lookup_symbols(void *transducer,
+                          const char *const *symbols,
+                          size_t n_symbols,
+                          const HfstLookupOptions *options,
+                          int *err) {
+    Lookup *result = nullptr;
+    guard(err, [&] {
+        std::vector<std::string> tokens(symbols, symbols + n_symbols);
+        result = lookup_in(*as_transducer(transducer),
+                           nullptr,
+                           tokens,
+                           options != nullptr ? *options : default_options());
+    });
+    return result;
+}
+
+void *hfst_lookup_up(void *transducer, const char *input, int *err) {
+    Lookup *result = nullptr;
+    guard(err, [&] {
+        const HfstTransducer *t = as_transducer(transducer);
+        if (is_optimized_lookup(*t)) {
+            throw Error{TRANSDUCER_TYPE_MISMATCH};
+        }
+        HfstTransducer inverted(*t);
+        inverted.invert();
+        inverted.convert(as_type(HFST_OLW_TYPE));
+        std::string text(input);
+        result = lookup_in(inverted, &text, {}, default_options());
+    });
+    return result;
+}
+
+void hfst_lookup_free(void *lookup) {
+    delete static_cast<Lookup *>(lookup);
+}
+
+bool hfst_lookup_timed_out(const void *lookup) {
+    return as_lookup(lookup)->timed_out;
+}
+
+bool hfst_lookup_was_infinitely_ambiguous(const void *lookup) {
+    return as_lookup(lookup)->infinitely_ambiguous;
+}
+
+bool hfst_is_lookup_infinitely_ambiguous(const void *transducer, const char *input) {
+    bool result = false;
+    int err = 0;
+    guard(&err, [&] {
+        HfstTransducer *t = as_transducer(const_cast<void *>(transducer));
+        if (is_optimized_lookup(*t)) {
+            result = t->is_lookup_infinitely_ambiguous(std::string(input));
+            return;
+        }
+        // the other types have no lookup of their own, so search them here
+        HfstBasicTransducer basic(*t);
+        HfstLookupOptions options = default_options();
+        options.check_infinitely_ambiguous = true;
+        options.unknown_symbols = UNKNOWN_SYMBOLS_IDENTITY;
+        std::vector<std::string> tokens = tokenize(input, basic.get_alphabet());
+        Lookup lookup;
+        Search(basic, tokens, options, lookup).run();
+        result = lookup.infinitely_ambiguous;
+    });
+    return result;
+}
+
+ResultIterator *hfst_lookup_iterator(void *lookup) {
+    ResultIterator *result = nullptr;
+    int err = 0;
+    guard(&err, [&] { result = new ResultIterator{as_lookup(lookup), 0}; });
+    return result;
+}
+
+bool hfst_lookup_iterator_done(ResultIterator *it) {
+    return it->position >= it->lookup->results.size();
+}
+
+void hfst_lookup_iterator_value(ResultIterator *it, char **s, float *w) {
+    *s = nullptr;
+    int err = 0;
+    guard(&err, [&] {
+        const LookupResult &result = current(it);
+        std::string output;
+        for (const std::string &symbol : result.symbols) {
+            output += symbol;
+        }
+        *s = copy_string(output);
+        *w = result.weight;
+    });
+}
+
+char **hfst_lookup_iterator_symbols(ResultIterator *it, size_t *n_symbols) {
+    char **result = nullptr;
+    *n_symbols = 0;
+    int err = 0;
+    guard(&err, [&] {
+        const LookupResult &current_result = current(it);
+        result = copy_strings(current_result.symbols);
+        *n_symbols = current_result.symbols.size();
+    });
+    return result;
+}
+
+char **hfst_lookup_iterator_alignment(ResultIterator *it, size_t *n_pairs) {
+    char **result = nullptr;
+    *n_pairs = 0;
+    if (!it->lookup->alignment) {
+        return nullptr;
+    }
+    int err = 0;
+    guard(&err, [&] {
+        const LookupResult &current_result = current(it);
+        std::vector<std::string> symbols;
+        for (const auto &pair : current_result.alignment) {
+            symbols.push_back(pair.first);
+            symbols.push_back(pair.second);
+        }
+        result = copy_strings(symbols);
+        *n_pairs = current_result.alignment.size();
+    });
+    return result;
+}
+
+HfstTraceArc *hfst_lookup_iterator_trace(ResultIterator *it, size_t *n_arcs) {
+    HfstTraceArc *result = nullptr;
+    *n_arcs = 0;
+    if (!it->lookup->trace) {
+        return nullptr;
+    }
+    int err = 0;
+    guard(&err, [&] {
+        const std::vector<TraceArc> &trace = current(it).trace;
+        size_t n = std::max<size_t>(trace.size(), 1);
+        HfstTraceArc *arcs = static_cast<HfstTraceArc *>(std::calloc(n, sizeof(HfstTraceArc)));
+        if (arcs == nullptr) {
+            throw std::bad_alloc();
+        }
+        try {
+            for (size_t i = 0; i < trace.size(); ++i) {
+                arcs[i].source = trace[i].source;
+                arcs[i].target = trace[i].target;
+                arcs[i].weight = trace[i].weight;
+                arcs[i].input = copy_string(trace[i].input);
+                arcs[i].output = copy_string(trace[i].output);
+            }
+        } catch (...) {
+            for (size_t i = 0; i < trace.size(); ++i) {
+                std::free(arcs[i].input);
+                std::free(arcs[i].output);
+            }
+            std::free(arcs);
+            throw;
+        }
+        result = arcs;
+        *n_arcs = trace.size();
+    });
+    return result;
+}
+
+void hfst_lookup_iterator_next(ResultIterator *it) {
+    ++it->position;
+}
+
+void hfst_lookup_iterator_free(ResultIterator *it) {
+    delete it;
+}
+
+} // extern "C"
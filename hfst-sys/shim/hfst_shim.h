@@ -0,0 +1,408 @@
+// The C API that hfst-sys binds to. It is implemented on top of the libhfst
+// C++ API in hfst_shim.cpp, which build.rs compiles and links into the
+// crate, so the only native library that is needed is libhfst itself.
+//
+// Conventions:
+//
+// - Transducers, input streams, output streams, basic transducers, lookups
+//   and iterators are opaque pointers (`void *`, or `ResultIterator *`).
+// - Functions that can fail take an `int *err` as their last argument. It is
+//   set to 0 on success, and to one of the error codes below on failure.
+// - Every pointer that is returned is owned by the caller, and must be freed
+//   with the matching function, see "Ownership" in the README. Strings and
+//   arrays of them are freed with `hfst_free`.
+// - No C++ exception ever leaves these functions.
+
+#ifndef HFST_SHIM_H
+#define HFST_SHIM_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <sys/types.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+// Error codes. Most of them are the exceptions of the same name in libhfst.
+#define NOT_TRANSDUCER_STREAM 1
+#define END_OF_STREAM 2
+#define IMPLEMENTATION_TYPE_NOT_AVAILABLE 3
+#define OTHER 4
+#define TRANSDUCER_TYPE_MISMATCH 5
+#define NOT_DETERMINIZABLE 6
+#define TRANSDUCER_IS_CYCLIC 7
+#define NOT_VALID_ATT_FORMAT 8
+#define XRE_COMPILE_ERROR 9
+#define TWOLC_COMPILE_ERROR 10
+// The lookup functions only look up in optimized-lookup transducers
+// (HFST_OL_TYPE and HFST_OLW_TYPE), and give this for other types. Convert
+// them with hfst_convert first.
+#define NOT_OPTIMIZED_FOR_LOOKUP 11
+
+// Implementation types, the same as hfst::ImplementationType.
+#define SFST_TYPE 0
+#define TROPICAL_OPENFST_TYPE 1
+#define LOG_OPENFST_TYPE 2
+#define FOMA_TYPE 3
+#define XFSM_TYPE 4
+#define HFST_OL_TYPE 5
+#define HFST_OLW_TYPE 6
+#define HFST2_TYPE 7
+#define UNSPECIFIED_TYPE 8
+#define ERROR_TYPE 9
+
+// Directions for hfst_push_weights, the same as hfst::PushType.
+#define TO_INITIAL_STATE 0
+#define TO_FINAL_STATE 1
+
+// Values of HfstLookupOptions.unknown_symbols.
+#define UNKNOWN_SYMBOLS_FAIL 0
+#define UNKNOWN_SYMBOLS_IDENTITY 1
+
+// Free a string, or an array, that one of these functions returned.
+void hfst_free(void *ptr);
+
+// ---------------------------------------------------------------------------
+// Input streams
+
+// Open the transducer file at `path`. Gives NULL, and sets `err` to
+// NOT_TRANSDUCER_STREAM if the file can not be opened, or is not a
+// transducer file.
+void *hfst_input_stream(const char *path, int *err);
+
+// Make an input stream of the `len` bytes at `data`. libhfst only reads from
+// files, so the data is written to a temporary file, which is removed again
+// when the stream is closed. The data is copied, so it can be freed as soon
+// as this returns. Gives NULL if `data` is not a transducer stream.
+void *hfst_input_stream_from_memory(const char *data, size_t len);
+
+// Close and free the stream.
+void hfst_input_stream_close(const void *input_stream);
+bool hfst_input_stream_is_eof(const void *input_stream);
+bool hfst_input_stream_is_bad(const void *input_stream);
+
+// The implementation type of the next transducer in the stream.
+int hfst_input_stream_type(const void *input_stream);
+
+// Read the next transducer of the stream. Gives NULL if it can not be read.
+// The transducer is a copy, and does not point into the stream, so it
+// outlives it.
+void *hfst_transducer_from_stream(const void *input_stream);
+
+// ---------------------------------------------------------------------------
+// Output streams
+
+// Create the file at `path`, for writing transducers of the type
+// `implementation_type` to it.
+void *hfst_output_stream(const char *path, int implementation_type, int *err);
+
+// Write `transducer`, which must be of the type of the stream.
+void hfst_output_stream_write(void *output_stream, const void *transducer, int *err);
+
+// Flush and close the stream. Free it with hfst_output_stream_free after.
+void hfst_output_stream_close(void *output_stream, int *err);
+void hfst_output_stream_free(void *output_stream);
+
+// ---------------------------------------------------------------------------
+// Transducers
+
+// An empty transducer (accepting nothing) of type TROPICAL_OPENFST_TYPE.
+void *hfst_empty_transducer(void);
+void *hfst_transducer_copy(const void *transducer);
+void hfst_transducer_free(void *transducer);
+int hfst_transducer_type(const void *transducer);
+
+// A transducer that maps each of the `n_pairs` strings of `inputs` to the
+// string of `outputs` at the same index, with the weight of `weights` at that
+// index. The strings are split into UTF-8 characters, and the shorter one of
+// each pair is padded with epsilons at the end.
+void *hfst_transducer_from_string_pairs(const char *const *inputs,
+                                        const char *const *outputs,
+                                        const float *weights,
+                                        size_t n_pairs,
+                                        int implementation_type,
+                                        int *err);
+
+// Read a transducer in AT&T format from the `len` bytes at `text`, where
+// epsilons are written as `epsilon`.
+void *hfst_transducer_from_att(const char *text,
+                               size_t len,
+                               const char *epsilon,
+                               int implementation_type,
+                               int *err);
+
+// The transducer in AT&T format, with epsilons written as `@0@`.
+char *hfst_transducer_to_att(const void *transducer, int *err);
+
+// Compile a regular expression, like hfst-regexp2fst. On XRE_COMPILE_ERROR,
+// `error_message` is set to the message of the compiler.
+void *hfst_xre_compile(const char *regex,
+                       int implementation_type,
+                       char **error_message,
+                       int *err);
+
+// Compile the twolc rules in the `len` bytes at `text`, like hfst-twolc.
+// Gives an array of `n_rules` transducers, one per rule. On
+// TWOLC_COMPILE_ERROR, `error_message` is set, but the details of the error
+// are only written to stderr by the compiler.
+void **hfst_twolc_compile(const char *text,
+                          size_t len,
+                          int implementation_type,
+                          size_t *n_rules,
+                          char **error_message,
+                          int *err);
+
+// ---------------------------------------------------------------------------
+// Operations. They all modify `transducer` in place, and leave `other` (or
+// `rules`) untouched. With `harmonize`, the alphabets are harmonized first.
+
+void hfst_compose(void *transducer, const void *other, bool harmonize, int *err);
+void hfst_disjunct(void *transducer, const void *other, bool harmonize, int *err);
+void hfst_intersect(void *transducer, const void *other, bool harmonize, int *err);
+void hfst_concatenate(void *transducer, const void *other, bool harmonize, int *err);
+void hfst_subtract(void *transducer, const void *other, bool harmonize, int *err);
+void hfst_minimize(void *transducer, int *err);
+
+// Without `weighted`, the weights are encoded into the arc labels, so that
+// determinization always finishes. With it, the weights are determinized as
+// well. As that may never finish for cyclic transducers, cyclic transducers
+// with weights on their arcs are refused with NOT_DETERMINIZABLE.
+void hfst_determinize(void *transducer, bool weighted, int *err);
+void hfst_remove_epsilons(void *transducer, int *err);
+void hfst_invert(void *transducer, int *err);
+void hfst_reverse(void *transducer, int *err);
+void hfst_input_project(void *transducer, int *err);
+void hfst_output_project(void *transducer, int *err);
+void hfst_repeat_star(void *transducer, int *err);
+void hfst_repeat_plus(void *transducer, int *err);
+void hfst_repeat_n(void *transducer, unsigned int n, int *err);
+void hfst_repeat_n_to_k(void *transducer, unsigned int n, unsigned int k, int *err);
+void hfst_optionalize(void *transducer, int *err);
+void hfst_n_best(void *transducer, unsigned int n, int *err);
+
+// `push_type` is TO_INITIAL_STATE or TO_FINAL_STATE.
+void hfst_push_weights(void *transducer, unsigned int push_type, int *err);
+
+// Remove the arcs and final weights that are not on any path with a weight of
+// at most `max_weight`, and the states that are left unreachable.
+void hfst_prune_weights(void *transducer, float max_weight, int *err);
+void hfst_insert_freely(void *transducer,
+                        const char *input,
+                        const char *output,
+                        bool harmonize,
+                        int *err);
+void hfst_insert_freely_transducer(void *transducer,
+                                   const void *other,
+                                   bool harmonize,
+                                   int *err);
+void hfst_compose_intersect(void *transducer,
+                            const void *const *rules,
+                            size_t n_rules,
+                            int *err);
+void hfst_eliminate_flags(void *transducer, int *err);
+void hfst_eliminate_flag(void *transducer, const char *name, int *err);
+void hfst_convert(void *transducer, int implementation_type, int *err);
+
+// Harmonize the alphabets of `transducer` and `other`. Modifies both.
+void hfst_harmonize(void *transducer, void *other, int *err);
+
+// Set the weight of every final state to `weight`, or add `weight` to it
+// with `increment`.
+void hfst_set_final_weights(void *transducer, float weight, bool increment, int *err);
+
+// Replace every weight `w` (on arcs and final states) with
+// `w * scale + offset`.
+void hfst_transform_weights(void *transducer, float scale, float offset, int *err);
+void hfst_prune_alphabet(void *transducer, int *err);
+
+// Remove the states that are not on any path from the initial state to a
+// final state.
+void hfst_prune(void *transducer, int *err);
+
+// ---------------------------------------------------------------------------
+// Queries
+
+bool hfst_is_cyclic(const void *transducer);
+
+typedef struct HfstTransducerStats {
+    size_t n_states;
+    size_t n_arcs;
+    size_t n_final_states;
+    // No state has an epsilon:epsilon arc, or two arcs with the same pair
+    // of symbols.
+    bool is_deterministic;
+    // Minimizing the transducer would not make it smaller.
+    bool is_minimal;
+    int implementation_type;
+} HfstTransducerStats;
+
+void hfst_transducer_stats(const void *transducer, HfstTransducerStats *stats, int *err);
+
+// Is the language of `transducer` included in the language of `other`?
+bool hfst_is_subset(const void *transducer, const void *other, int *err);
+
+// Do the transducers have the same paths with the same weights?
+bool hfst_compare(const void *transducer, const void *other, bool harmonize, int *err);
+
+// Every symbol of the alphabet, as an array of `n_symbols` strings.
+char **hfst_alphabet(const void *transducer, size_t *n_symbols);
+
+// The name is empty if the transducer has none.
+char *hfst_get_name(const void *transducer);
+void hfst_set_name(void *transducer, const char *name);
+
+// Gives NULL if the transducer does not have the property `key`.
+char *hfst_get_property(const void *transducer, const char *key);
+void hfst_set_property(void *transducer, const char *key, const char *value);
+char **hfst_property_keys(const void *transducer, size_t *n_keys);
+
+// ---------------------------------------------------------------------------
+// Paths. The paths iterators own the strings they give, which are only valid
+// until hfst_paths_iterator_next or hfst_paths_iterator_free is called.
+
+// The paths of the transducer, sorted by weight. At most `max_num` paths are
+// extracted, and each cycle is followed at most `cycles` times, where -1
+// means no limit. Gives TRANSDUCER_IS_CYCLIC if the transducer is cyclic,
+// and `cycles` is -1.
+void *hfst_extract_paths(const void *transducer, int max_num, int cycles, int *err);
+
+// At most `max_num` random paths. Paths that the flag diacritics do not
+// allow are left out with `obey_flags`.
+void *hfst_extract_random_paths(const void *transducer,
+                                int max_num,
+                                bool obey_flags,
+                                int *err);
+
+// The lightest path, or no path at all if the transducer accepts nothing.
+void *hfst_shortest_path(const void *transducer, int *err);
+bool hfst_paths_iterator_done(void *it);
+
+// Epsilons are left out of `input` and `output`.
+void hfst_paths_iterator_value(void *it, char **input, char **output, float *w);
+void hfst_paths_iterator_next(void *it);
+void hfst_paths_iterator_free(void *it);
+
+// ---------------------------------------------------------------------------
+// Basic transducers, for building transducers state by state
+
+void *hfst_basic_transducer_new(void);
+void hfst_basic_transducer_free(void *transducer);
+unsigned int hfst_basic_transducer_add_state(void *transducer);
+
+// States that do not exist yet are added.
+void hfst_basic_transducer_add_transition(void *transducer,
+                                          unsigned int source,
+                                          unsigned int target,
+                                          const char *input,
+                                          const char *output,
+                                          float weight);
+void hfst_basic_transducer_set_final_weight(void *transducer,
+                                            unsigned int state,
+                                            float weight);
+void *hfst_basic_transducer_to_transducer(const void *transducer,
+                                          int implementation_type,
+                                          int *err);
+
+// ---------------------------------------------------------------------------
+// Lookup
+//
+// A lookup holds all of its results, and an iterator reads them off it. The
+// lookup does not point into the transducer after it has been made, but free
+// it before the transducer anyway, as the hfst crate does.
+//
+// Plain lookups use the lookup of libhfst's optimized-lookup transducers.
+// Lookups with `alignment` or `trace`, or without `obey_flags`, need more
+// than that gives, so they are done by the lookup in hfst_shim.cpp instead,
+// on a copy of the transducer that is converted to a basic transducer first.
+// That is slow for large transducers, as those options are meant for
+// debugging.
+
+typedef struct HfstLookupOptions {
+    // Stop after this many results, or -1 for no limit.
+    ssize_t max_results;
+    // Stop after this many seconds, or 0.0 for no limit.
+    double time_cutoff;
+    bool obey_flags;
+    // Check that the input does not give infinitely many results first, and
+    // give no results at all if it does.
+    bool check_infinitely_ambiguous;
+    bool alignment;
+    // Leave out results with a larger weight. INFINITY for no limit.
+    float max_weight;
+    bool trace;
+    // UNKNOWN_SYMBOLS_FAIL or UNKNOWN_SYMBOLS_IDENTITY.
+    int unknown_symbols;
+} HfstLookupOptions;
+
+typedef struct HfstTraceArc {
+    unsigned int source;
+    unsigned int target;
+    // Epsilons are empty strings.
+    char *input;
+    char *output;
+    float weight;
+} HfstTraceArc;
+
+typedef struct ResultIterator ResultIterator;
+
+// Look up `input`, with the default options (no limits, obeying flags,
+// failing on unknown symbols).
+void *hfst_lookup(void *transducer, const char *input, int *err);
+void *hfst_lookup_with_options(void *transducer,
+                               const char *input,
+                               const HfstLookupOptions *options,
+                               int *err);
+
+// Look up the `n_symbols` symbols of `symbols`, without splitting them.
+void *hfst_lookup_symbols(void *transducer,
+                          const char *const *symbols,
+                          size_t n_symbols,
+                          const HfstLookupOptions *options,
+                          int *err);
+
+// Look up `input` on the output side of the transducer (apply up). This is
+// done in an inverted copy of the transducer, that is converted to
+// HFST_OLW_TYPE. Optimized-lookup transducers can not be inverted, and give
+// TRANSDUCER_TYPE_MISMATCH.
+void *hfst_lookup_up(void *transducer, const char *input, int *err);
+void hfst_lookup_free(void *lookup);
+
+// Was the lookup cut off by `time_cutoff`?
+bool hfst_lookup_timed_out(const void *lookup);
+
+// Was the lookup not done, because `check_infinitely_ambiguous` found that
+// the input would give infinitely many results?
+bool hfst_lookup_was_infinitely_ambiguous(const void *lookup);
+
+bool hfst_is_lookup_infinitely_ambiguous(const void *transducer, const char *input);
+
+ResultIterator *hfst_lookup_iterator(void *lookup);
+bool hfst_lookup_iterator_done(ResultIterator *it);
+
+// The output of the current result, and its weight. The output is the
+// output symbols joined together, with the epsilons left out, but the flag
+// diacritics kept.
+void hfst_lookup_iterator_value(ResultIterator *it, char **s, float *w);
+
+// The output symbols of the current result, as an array of `n_symbols`
+// strings.
+char **hfst_lookup_iterator_symbols(ResultIterator *it, size_t *n_symbols);
+
+// The input and output symbols of each arc of the path of the current
+// result, alternating, as an array of `2 * n_pairs` strings. Epsilons are
+// empty strings. Gives NULL, unless the lookup was made with `alignment`.
+char **hfst_lookup_iterator_alignment(ResultIterator *it, size_t *n_pairs);
+
+// The arcs of the path of the current result, as an array of `n_arcs` arcs,
+// whose strings are freed with hfst_free as well. Gives NULL, unless the
+// lookup was made with `trace`.
+HfstTraceArc *hfst_lookup_iterator_trace(ResultIterator *it, size_t *n_arcs);
+void hfst_lookup_iterator_next(ResultIterator *it);
+void hfst_lookup_iterator_free(ResultIterator *it);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
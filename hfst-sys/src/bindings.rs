@@ -1,92 +1,623 @@
 /* automatically generated by rust-bindgen 0.72.1 */
 
+pub const NOT_TRANSDUCER_STREAM: u32 = 1;
+pub const END_OF_STREAM: u32 = 2;
+pub const IMPLEMENTATION_TYPE_NOT_AVAILABLE: u32 = 3;
+pub const OTHER: u32 = 4;
+pub const TRANSDUCER_TYPE_MISMATCH: u32 = 5;
+pub const NOT_DETERMINIZABLE: u32 = 6;
+pub const TRANSDUCER_IS_CYCLIC: u32 = 7;
+pub const NOT_VALID_ATT_FORMAT: u32 = 8;
+pub const XRE_COMPILE_ERROR: u32 = 9;
+pub const TWOLC_COMPILE_ERROR: u32 = 10;
+pub const NOT_OPTIMIZED_FOR_LOOKUP: u32 = 11;
+pub const SFST_TYPE: u32 = 0;
+pub const TROPICAL_OPENFST_TYPE: u32 = 1;
+pub const LOG_OPENFST_TYPE: u32 = 2;
+pub const FOMA_TYPE: u32 = 3;
+pub const XFSM_TYPE: u32 = 4;
+pub const HFST_OL_TYPE: u32 = 5;
+pub const HFST_OLW_TYPE: u32 = 6;
+pub const HFST2_TYPE: u32 = 7;
+pub const UNSPECIFIED_TYPE: u32 = 8;
+pub const ERROR_TYPE: u32 = 9;
+pub const TO_INITIAL_STATE: u32 = 0;
+pub const TO_FINAL_STATE: u32 = 1;
+pub const UNKNOWN_SYMBOLS_FAIL: u32 = 0;
+pub const UNKNOWN_SYMBOLS_IDENTITY: u32 = 1;
 unsafe extern "C" {
-    pub fn hfst_empty_transducer() -> *mut ::std::os::raw::c_void;
+    pub fn hfst_free(ptr: *mut ::std::os::raw::c_void);
 }
-pub type hfst_empty_transducer_t =
-    ::std::option::Option<unsafe extern "C" fn() -> *mut ::std::os::raw::c_void>;
 unsafe extern "C" {
-    pub fn hfst_input_stream(path: *const ::std::os::raw::c_char) -> *mut ::std::os::raw::c_void;
+    pub fn hfst_input_stream(
+        path: *const ::std::os::raw::c_char,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_input_stream_from_memory(
+        data: *const ::std::os::raw::c_char,
+        len: usize,
+    ) -> *mut ::std::os::raw::c_void;
 }
-pub type hfst_input_stream_t = ::std::option::Option<
-    unsafe extern "C" fn(filename: *const ::std::os::raw::c_char) -> *mut ::std::os::raw::c_void,
->;
 unsafe extern "C" {
     pub fn hfst_input_stream_close(input_stream: *const ::std::os::raw::c_void);
 }
-pub type hfst_input_stream_close_t = ::std::option::Option<
-    unsafe extern "C" fn(arg1: *mut ::std::os::raw::c_void) -> *mut ::std::os::raw::c_void,
->;
 unsafe extern "C" {
-    pub fn hfst_input_stream_is_eof(arg1: *const ::std::os::raw::c_void) -> bool;
+    pub fn hfst_input_stream_is_eof(input_stream: *const ::std::os::raw::c_void) -> bool;
 }
-pub type hfst_input_stream_is_eof_t =
-    ::std::option::Option<unsafe extern "C" fn(arg1: *mut ::std::os::raw::c_void) -> bool>;
 unsafe extern "C" {
-    pub fn hfst_input_stream_is_bad(arg1: *const ::std::os::raw::c_void) -> bool;
+    pub fn hfst_input_stream_is_bad(input_stream: *const ::std::os::raw::c_void) -> bool;
+}
+unsafe extern "C" {
+    pub fn hfst_input_stream_type(
+        input_stream: *const ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_int;
 }
-pub type hfst_input_stream_is_bad_t =
-    ::std::option::Option<unsafe extern "C" fn(arg1: *mut ::std::os::raw::c_void) -> bool>;
 unsafe extern "C" {
     pub fn hfst_transducer_from_stream(
         input_stream: *const ::std::os::raw::c_void,
     ) -> *mut ::std::os::raw::c_void;
 }
-pub type hfst_transducer_from_stream_t = ::std::option::Option<
-    unsafe extern "C" fn(arg1: *mut ::std::os::raw::c_void) -> *mut ::std::os::raw::c_void,
->;
 unsafe extern "C" {
-    pub fn hfst_lookup_begin(arg1: *const ::std::os::raw::c_void) -> *mut ::std::os::raw::c_void;
+    pub fn hfst_output_stream(
+        path: *const ::std::os::raw::c_char,
+        implementation_type: ::std::os::raw::c_int,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_output_stream_write(
+        output_stream: *mut ::std::os::raw::c_void,
+        transducer: *const ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
 }
-pub type hfst_lookup_begin_t = ::std::option::Option<
-    unsafe extern "C" fn(arg1: *const ::std::os::raw::c_void) -> *mut ::std::os::raw::c_void,
->;
 unsafe extern "C" {
-    pub fn hfst_lookup_results(
-        arg1: *const ::std::os::raw::c_void,
-        arg2: *mut *mut ::std::os::raw::c_char,
-        arg3: *mut f32,
-    ) -> usize;
+    pub fn hfst_output_stream_close(
+        output_stream: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
 }
-pub type hfst_lookup_results_t = ::std::option::Option<
-    unsafe extern "C" fn(
-        arg1: *mut ::std::os::raw::c_void,
-        arg2: *mut *mut ::std::os::raw::c_char,
-        arg3: *mut f32,
-    ) -> usize,
->;
 unsafe extern "C" {
-    pub fn hfst_lookup(
+    pub fn hfst_output_stream_free(output_stream: *mut ::std::os::raw::c_void);
+}
+unsafe extern "C" {
+    pub fn hfst_empty_transducer() -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_transducer_copy(
+        transducer: *const ::std::os::raw::c_void,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_transducer_free(transducer: *mut ::std::os::raw::c_void);
+}
+unsafe extern "C" {
+    pub fn hfst_transducer_type(transducer: *const ::std::os::raw::c_void)
+    -> ::std::os::raw::c_int;
+}
+unsafe extern "C" {
+    pub fn hfst_transducer_from_string_pairs(
+        inputs: *const *const ::std::os::raw::c_char,
+        outputs: *const *const ::std::os::raw::c_char,
+        weights: *const f32,
+        n_pairs: usize,
+        implementation_type: ::std::os::raw::c_int,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_transducer_from_att(
+        text: *const ::std::os::raw::c_char,
+        len: usize,
+        epsilon: *const ::std::os::raw::c_char,
+        implementation_type: ::std::os::raw::c_int,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_transducer_to_att(
+        transducer: *const ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_char;
+}
+unsafe extern "C" {
+    pub fn hfst_xre_compile(
+        regex: *const ::std::os::raw::c_char,
+        implementation_type: ::std::os::raw::c_int,
+        error_message: *mut *mut ::std::os::raw::c_char,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_twolc_compile(
+        text: *const ::std::os::raw::c_char,
+        len: usize,
+        implementation_type: ::std::os::raw::c_int,
+        n_rules: *mut usize,
+        error_message: *mut *mut ::std::os::raw::c_char,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_compose(
+        transducer: *mut ::std::os::raw::c_void,
+        other: *const ::std::os::raw::c_void,
+        harmonize: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_disjunct(
+        transducer: *mut ::std::os::raw::c_void,
+        other: *const ::std::os::raw::c_void,
+        harmonize: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_intersect(
+        transducer: *mut ::std::os::raw::c_void,
+        other: *const ::std::os::raw::c_void,
+        harmonize: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_concatenate(
+        transducer: *mut ::std::os::raw::c_void,
+        other: *const ::std::os::raw::c_void,
+        harmonize: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_subtract(
+        transducer: *mut ::std::os::raw::c_void,
+        other: *const ::std::os::raw::c_void,
+        harmonize: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_minimize(transducer: *mut ::std::os::raw::c_void, err: *mut ::std::os::raw::c_int);
+}
+unsafe extern "C" {
+    pub fn hfst_determinize(
+        transducer: *mut ::std::os::raw::c_void,
+        weighted: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_remove_epsilons(
+        transducer: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_invert(transducer: *mut ::std::os::raw::c_void, err: *mut ::std::os::raw::c_int);
+}
+unsafe extern "C" {
+    pub fn hfst_reverse(transducer: *mut ::std::os::raw::c_void, err: *mut ::std::os::raw::c_int);
+}
+unsafe extern "C" {
+    pub fn hfst_input_project(
+        transducer: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_output_project(
+        transducer: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_repeat_star(
+        transducer: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_repeat_plus(
+        transducer: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_repeat_n(
+        transducer: *mut ::std::os::raw::c_void,
+        n: ::std::os::raw::c_uint,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_repeat_n_to_k(
+        transducer: *mut ::std::os::raw::c_void,
+        n: ::std::os::raw::c_uint,
+        k: ::std::os::raw::c_uint,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_optionalize(
+        transducer: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_n_best(
+        transducer: *mut ::std::os::raw::c_void,
+        n: ::std::os::raw::c_uint,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_push_weights(
+        transducer: *mut ::std::os::raw::c_void,
+        push_type: ::std::os::raw::c_uint,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_prune_weights(
+        transducer: *mut ::std::os::raw::c_void,
+        max_weight: f32,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_insert_freely(
         transducer: *mut ::std::os::raw::c_void,
         input: *const ::std::os::raw::c_char,
+        output: *const ::std::os::raw::c_char,
+        harmonize: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_insert_freely_transducer(
+        transducer: *mut ::std::os::raw::c_void,
+        other: *const ::std::os::raw::c_void,
+        harmonize: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_compose_intersect(
+        transducer: *mut ::std::os::raw::c_void,
+        rules: *const *const ::std::os::raw::c_void,
+        n_rules: usize,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_eliminate_flags(
+        transducer: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_eliminate_flag(
+        transducer: *mut ::std::os::raw::c_void,
+        name: *const ::std::os::raw::c_char,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_convert(
+        transducer: *mut ::std::os::raw::c_void,
+        implementation_type: ::std::os::raw::c_int,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_harmonize(
+        transducer: *mut ::std::os::raw::c_void,
+        other: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_set_final_weights(
+        transducer: *mut ::std::os::raw::c_void,
+        weight: f32,
+        increment: bool,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_transform_weights(
+        transducer: *mut ::std::os::raw::c_void,
+        scale: f32,
+        offset: f32,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_prune_alphabet(
+        transducer: *mut ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_prune(transducer: *mut ::std::os::raw::c_void, err: *mut ::std::os::raw::c_int);
+}
+unsafe extern "C" {
+    pub fn hfst_is_cyclic(transducer: *const ::std::os::raw::c_void) -> bool;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct HfstTransducerStats {
+    pub n_states: usize,
+    pub n_arcs: usize,
+    pub n_final_states: usize,
+    pub is_deterministic: bool,
+    pub is_minimal: bool,
+    pub implementation_type: ::std::os::raw::c_int,
+}
+#[allow(clippy::unnecessary_operation, clippy::identity_op)]
+const _: () = {
+    ["Size of HfstTransducerStats"][::std::mem::size_of::<HfstTransducerStats>() - 32usize];
+    ["Alignment of HfstTransducerStats"][::std::mem::align_of::<HfstTransducerStats>() - 8usize];
+    ["Offset of field: HfstTransducerStats::n_states"]
+        [::std::mem::offset_of!(HfstTransducerStats, n_states) - 0usize];
+    ["Offset of field: HfstTransducerStats::n_arcs"]
+        [::std::mem::offset_of!(HfstTransducerStats, n_arcs) - 8usize];
+    ["Offset of field: HfstTransducerStats::n_final_states"]
+        [::std::mem::offset_of!(HfstTransducerStats, n_final_states) - 16usize];
+    ["Offset of field: HfstTransducerStats::is_deterministic"]
+        [::std::mem::offset_of!(HfstTransducerStats, is_deterministic) - 24usize];
+    ["Offset of field: HfstTransducerStats::is_minimal"]
+        [::std::mem::offset_of!(HfstTransducerStats, is_minimal) - 25usize];
+    ["Offset of field: HfstTransducerStats::implementation_type"]
+        [::std::mem::offset_of!(HfstTransducerStats, implementation_type) - 28usize];
+};
+unsafe extern "C" {
+    pub fn hfst_transducer_stats(
+        transducer: *const ::std::os::raw::c_void,
+        stats: *mut HfstTransducerStats,
+        err: *mut ::std::os::raw::c_int,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_is_subset(
+        transducer: *const ::std::os::raw::c_void,
+        other: *const ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    ) -> bool;
+}
+unsafe extern "C" {
+    pub fn hfst_compare(
+        transducer: *const ::std::os::raw::c_void,
+        other: *const ::std::os::raw::c_void,
+        harmonize: bool,
+        err: *mut ::std::os::raw::c_int,
+    ) -> bool;
+}
+unsafe extern "C" {
+    pub fn hfst_alphabet(
+        transducer: *const ::std::os::raw::c_void,
+        n_symbols: *mut usize,
+    ) -> *mut *mut ::std::os::raw::c_char;
+}
+unsafe extern "C" {
+    pub fn hfst_get_name(transducer: *const ::std::os::raw::c_void) -> *mut ::std::os::raw::c_char;
+}
+unsafe extern "C" {
+    pub fn hfst_set_name(
+        transducer: *mut ::std::os::raw::c_void,
+        name: *const ::std::os::raw::c_char,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_get_property(
+        transducer: *const ::std::os::raw::c_void,
+        key: *const ::std::os::raw::c_char,
+    ) -> *mut ::std::os::raw::c_char;
+}
+unsafe extern "C" {
+    pub fn hfst_set_property(
+        transducer: *mut ::std::os::raw::c_void,
+        key: *const ::std::os::raw::c_char,
+        value: *const ::std::os::raw::c_char,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_property_keys(
+        transducer: *const ::std::os::raw::c_void,
+        n_keys: *mut usize,
+    ) -> *mut *mut ::std::os::raw::c_char;
+}
+unsafe extern "C" {
+    pub fn hfst_extract_paths(
+        transducer: *const ::std::os::raw::c_void,
+        max_num: ::std::os::raw::c_int,
+        cycles: ::std::os::raw::c_int,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_extract_random_paths(
+        transducer: *const ::std::os::raw::c_void,
+        max_num: ::std::os::raw::c_int,
+        obey_flags: bool,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_shortest_path(
+        transducer: *const ::std::os::raw::c_void,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_paths_iterator_done(it: *mut ::std::os::raw::c_void) -> bool;
+}
+unsafe extern "C" {
+    pub fn hfst_paths_iterator_value(
+        it: *mut ::std::os::raw::c_void,
+        input: *mut *mut ::std::os::raw::c_char,
+        output: *mut *mut ::std::os::raw::c_char,
+        w: *mut f32,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_paths_iterator_next(it: *mut ::std::os::raw::c_void);
+}
+unsafe extern "C" {
+    pub fn hfst_paths_iterator_free(it: *mut ::std::os::raw::c_void);
+}
+unsafe extern "C" {
+    pub fn hfst_basic_transducer_new() -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_basic_transducer_free(transducer: *mut ::std::os::raw::c_void);
+}
+unsafe extern "C" {
+    pub fn hfst_basic_transducer_add_state(
+        transducer: *mut ::std::os::raw::c_void,
+    ) -> ::std::os::raw::c_uint;
+}
+unsafe extern "C" {
+    pub fn hfst_basic_transducer_add_transition(
+        transducer: *mut ::std::os::raw::c_void,
+        source: ::std::os::raw::c_uint,
+        target: ::std::os::raw::c_uint,
+        input: *const ::std::os::raw::c_char,
+        output: *const ::std::os::raw::c_char,
+        weight: f32,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_basic_transducer_set_final_weight(
+        transducer: *mut ::std::os::raw::c_void,
+        state: ::std::os::raw::c_uint,
+        weight: f32,
+    );
+}
+unsafe extern "C" {
+    pub fn hfst_basic_transducer_to_transducer(
+        transducer: *const ::std::os::raw::c_void,
+        implementation_type: ::std::os::raw::c_int,
+        err: *mut ::std::os::raw::c_int,
     ) -> *mut ::std::os::raw::c_void;
 }
-pub type hfst_lookup_t = ::std::option::Option<
-    unsafe extern "C" fn(
-        arg1: *mut ::std::os::raw::c_void,
-        arg2: *const ::std::os::raw::c_char,
-    ) -> *mut ::std::os::raw::c_void,
->;
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
-pub struct ResultIterator {
-    pub begin: *mut ::std::os::raw::c_void,
-    pub end: *mut ::std::os::raw::c_void,
+pub struct HfstLookupOptions {
+    pub max_results: isize,
+    pub time_cutoff: f64,
+    pub obey_flags: bool,
+    pub check_infinitely_ambiguous: bool,
+    pub alignment: bool,
+    pub max_weight: f32,
+    pub trace: bool,
+    pub unknown_symbols: ::std::os::raw::c_int,
+}
+#[allow(clippy::unnecessary_operation, clippy::identity_op)]
+const _: () = {
+    ["Size of HfstLookupOptions"][::std::mem::size_of::<HfstLookupOptions>() - 32usize];
+    ["Alignment of HfstLookupOptions"][::std::mem::align_of::<HfstLookupOptions>() - 8usize];
+    ["Offset of field: HfstLookupOptions::max_results"]
+        [::std::mem::offset_of!(HfstLookupOptions, max_results) - 0usize];
+    ["Offset of field: HfstLookupOptions::time_cutoff"]
+        [::std::mem::offset_of!(HfstLookupOptions, time_cutoff) - 8usize];
+    ["Offset of field: HfstLookupOptions::obey_flags"]
+        [::std::mem::offset_of!(HfstLookupOptions, obey_flags) - 16usize];
+    ["Offset of field: HfstLookupOptions::check_infinitely_ambiguous"]
+        [::std::mem::offset_of!(HfstLookupOptions, check_infinitely_ambiguous) - 17usize];
+    ["Offset of field: HfstLookupOptions::alignment"]
+        [::std::mem::offset_of!(HfstLookupOptions, alignment) - 18usize];
+    ["Offset of field: HfstLookupOptions::max_weight"]
+        [::std::mem::offset_of!(HfstLookupOptions, max_weight) - 20usize];
+    ["Offset of field: HfstLookupOptions::trace"]
+        [::std::mem::offset_of!(HfstLookupOptions, trace) - 24usize];
+    ["Offset of field: HfstLookupOptions::unknown_symbols"]
+        [::std::mem::offset_of!(HfstLookupOptions, unknown_symbols) - 28usize];
+};
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct HfstTraceArc {
+    pub source: ::std::os::raw::c_uint,
+    pub target: ::std::os::raw::c_uint,
+    pub input: *mut ::std::os::raw::c_char,
+    pub output: *mut ::std::os::raw::c_char,
+    pub weight: f32,
 }
 #[allow(clippy::unnecessary_operation, clippy::identity_op)]
 const _: () = {
-    ["Size of ResultIterator"][::std::mem::size_of::<ResultIterator>() - 16usize];
-    ["Alignment of ResultIterator"][::std::mem::align_of::<ResultIterator>() - 8usize];
-    ["Offset of field: ResultIterator::begin"]
-        [::std::mem::offset_of!(ResultIterator, begin) - 0usize];
-    ["Offset of field: ResultIterator::end"][::std::mem::offset_of!(ResultIterator, end) - 8usize];
+    ["Size of HfstTraceArc"][::std::mem::size_of::<HfstTraceArc>() - 32usize];
+    ["Alignment of HfstTraceArc"][::std::mem::align_of::<HfstTraceArc>() - 8usize];
+    ["Offset of field: HfstTraceArc::source"]
+        [::std::mem::offset_of!(HfstTraceArc, source) - 0usize];
+    ["Offset of field: HfstTraceArc::target"]
+        [::std::mem::offset_of!(HfstTraceArc, target) - 4usize];
+    ["Offset of field: HfstTraceArc::input"][::std::mem::offset_of!(HfstTraceArc, input) - 8usize];
+    ["Offset of field: HfstTraceArc::output"]
+        [::std::mem::offset_of!(HfstTraceArc, output) - 16usize];
+    ["Offset of field: HfstTraceArc::weight"]
+        [::std::mem::offset_of!(HfstTraceArc, weight) - 24usize];
 };
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ResultIterator {
+    _unused: [u8; 0],
+}
+unsafe extern "C" {
+    pub fn hfst_lookup(
+        transducer: *mut ::std::os::raw::c_void,
+        input: *const ::std::os::raw::c_char,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_lookup_with_options(
+        transducer: *mut ::std::os::raw::c_void,
+        input: *const ::std::os::raw::c_char,
+        options: *const HfstLookupOptions,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_lookup_symbols(
+        transducer: *mut ::std::os::raw::c_void,
+        symbols: *const *const ::std::os::raw::c_char,
+        n_symbols: usize,
+        options: *const HfstLookupOptions,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_lookup_up(
+        transducer: *mut ::std::os::raw::c_void,
+        input: *const ::std::os::raw::c_char,
+        err: *mut ::std::os::raw::c_int,
+    ) -> *mut ::std::os::raw::c_void;
+}
+unsafe extern "C" {
+    pub fn hfst_lookup_free(lookup: *mut ::std::os::raw::c_void);
+}
+unsafe extern "C" {
+    pub fn hfst_lookup_timed_out(lookup: *const ::std::os::raw::c_void) -> bool;
+}
+unsafe extern "C" {
+    pub fn hfst_lookup_was_infinitely_ambiguous(lookup: *const ::std::os::raw::c_void) -> bool;
+}
+unsafe extern "C" {
+    pub fn hfst_is_lookup_infinitely_ambiguous(
+        transducer: *const ::std::os::raw::c_void,
+        input: *const ::std::os::raw::c_char,
+    ) -> bool;
+}
 unsafe extern "C" {
     pub fn hfst_lookup_iterator(lookup: *mut ::std::os::raw::c_void) -> *mut ResultIterator;
 }
-pub type hfst_lookup_iterator_t = ::std::option::Option<
-    unsafe extern "C" fn(arg1: *mut ::std::os::raw::c_void) -> *mut ResultIterator,
->;
+unsafe extern "C" {
+    pub fn hfst_lookup_iterator_done(it: *mut ResultIterator) -> bool;
+}
 unsafe extern "C" {
     pub fn hfst_lookup_iterator_value(
         it: *mut ResultIterator,
@@ -94,25 +625,27 @@ unsafe extern "C" {
         w: *mut f32,
     );
 }
-pub type hfst_lookup_iterator_value_t = ::std::option::Option<
-    unsafe extern "C" fn(
+unsafe extern "C" {
+    pub fn hfst_lookup_iterator_symbols(
         it: *mut ResultIterator,
-        s: *mut *mut ::std::os::raw::c_char,
-        weight: *mut f32,
-    ),
->;
+        n_symbols: *mut usize,
+    ) -> *mut *mut ::std::os::raw::c_char;
+}
 unsafe extern "C" {
-    pub fn hfst_lookup_iterator_next(it: *mut ResultIterator);
+    pub fn hfst_lookup_iterator_alignment(
+        it: *mut ResultIterator,
+        n_pairs: *mut usize,
+    ) -> *mut *mut ::std::os::raw::c_char;
 }
-pub type hfst_lookup_iterator_next_t =
-    ::std::option::Option<unsafe extern "C" fn(arg1: *mut ResultIterator)>;
 unsafe extern "C" {
-    pub fn hfst_lookup_iterator_free(it: *mut ResultIterator);
+    pub fn hfst_lookup_iterator_trace(
+        it: *mut ResultIterator,
+        n_arcs: *mut usize,
+    ) -> *mut HfstTraceArc;
 }
-pub type hfst_lookup_iterator_free_t =
-    ::std::option::Option<unsafe extern "C" fn(it: *mut ResultIterator)>;
 unsafe extern "C" {
-    pub fn hfst_lookup_iterator_done(it: *mut ResultIterator) -> bool;
+    pub fn hfst_lookup_iterator_next(it: *mut ResultIterator);
+}
+unsafe extern "C" {
+    pub fn hfst_lookup_iterator_free(it: *mut ResultIterator);
 }
-pub type hfst_lookup_iterator_done_t =
-    ::std::option::Option<unsafe extern "C" fn(it: *mut ResultIterator) -> bool>;
@@ -11,7 +11,7 @@ include!("bindings.rs");
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{os::raw::c_char, str::Utf8Error};
+    use std::{os::raw::{c_char, c_int}, str::Utf8Error};
 
     /// Length of c string
     fn strlen(s: *const c_char) -> usize {
@@ -77,7 +77,8 @@ mod tests {
     #[ignore]
     fn open_read_close() -> Result<(), String> {
         let path = "/usr/share/giella/sme/analyser-dict-gt-desc.hfstol\0";
-        let input_stream = unsafe { hfst_input_stream(str_as_cstr(path)) };
+        let mut err: c_int = 0;
+        let input_stream = unsafe { hfst_input_stream(str_as_cstr(path), &raw mut err) };
         if input_stream.is_null() {
             return Err(format!("input_stream was NULL"));
         }
@@ -95,7 +96,9 @@ mod tests {
         expected_analyses.insert("viessu+N+Sg+Nom", false);
 
         let lookup_str = str_as_cstr("viessu\0");
-        let lookup = unsafe { hfst_lookup(tr, lookup_str) };
+        let mut err: c_int = 0;
+        let lookup = unsafe { hfst_lookup(tr, lookup_str, &raw mut err) };
+        assert_eq!(err, 0);
         let iter = unsafe { hfst_lookup_iterator(lookup) };
 
         unsafe {
@@ -109,6 +112,8 @@ mod tests {
                 );
 
                 let seen_analysis = c_charptr_as_str_unchecked(s).remove_ats();
+                // the string is ours, but it was allocated by libhfst
+                hfst_free(s as *mut std::os::raw::c_void);
                 println!("{}", seen_analysis);
                 let Some(v) = expected_analyses.get_mut(seen_analysis.as_str()) else {
                     panic!("got an analysis we did not expect: {}", seen_analysis);
@@ -129,8 +134,10 @@ mod tests {
     #[test]
     fn input_stream_nonexistant_fails() -> Result<(), String> {
         let path = "/this/does/not/exist\0";
-        let input_stream = unsafe { hfst_input_stream(str_as_cstr(path)) };
+        let mut err: c_int = 0;
+        let input_stream = unsafe { hfst_input_stream(str_as_cstr(path), &raw mut err) };
         assert!(input_stream.is_null());
+        assert_eq!(err as u32, NOT_TRANSDUCER_STREAM);
         Ok(())
     }
 
@@ -1 +1 @@
-#include <hfst/libhfst_c.h>
+#include "shim/hfst_shim.h"
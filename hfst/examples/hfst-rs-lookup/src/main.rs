@@ -2,9 +2,8 @@ use std::io::{self, BufRead};
 use std::time::Instant;
 
 use clap::Parser;
-use itertools::Itertools;
 
-use hfst::HfstInputStream;
+use hfst::{strip_flags, HfstInputStream};
 
 /// Simple version of hfst-lookup, written in Rust
 #[derive(Parser, Debug)]
@@ -26,15 +25,15 @@ fn main() -> Result<(), String> {
         return Err(format!("can't read hfst from file '{}'", hfst.display()));
     };
 
-    let transducers = is.read_transducers();
+    let transducer = match is.into_iter().next() {
+        Some(Ok(transducer)) => transducer,
+        Some(Err(e)) => return Err(format!("can't read transducer: {e}")),
+        None => return Err("expected at least 1 transducer in hfst".to_string()),
+    };
     if verbose {
         println!("loaded in {:?}", Instant::now().duration_since(t0));
     }
 
-    let Some(transducer) = transducers.first() else {
-        return Err("expected at least 1 transducer in hfst".to_string());
-    };
-
     let stdin = io::stdin();
     for line in stdin.lock().lines() {
         let Ok(line) = line else {
@@ -42,9 +41,16 @@ fn main() -> Result<(), String> {
         };
         let t0 = Instant::now();
         let mut n = 0;
-        for (s, w) in transducer.lookup(&line) {
-            let without_ats = remove_ats(&s);
-            println!("{line} → {without_ats} {w}");
+        let lookup = match transducer.lookup(&line) {
+            Ok(lookup) => lookup,
+            Err(e) => return Err(format!("can't look up {line}: {e}")),
+        };
+        for result in lookup {
+            let Ok(result) = result else {
+                return Err(format!("lookup result for {line} is not valid UTF-8"));
+            };
+            let without_flags = strip_flags(&result.output);
+            println!("{line} → {without_flags} {}", result.weight);
             n += 1;
         }
         if n == 0 {
@@ -57,19 +63,3 @@ fn main() -> Result<(), String> {
 
     Ok(())
 }
-
-fn remove_ats(s: &str) -> String {
-    let at_positions = s
-        .char_indices()
-        .filter_map(|(pos, ch)| (ch == '@').then_some(pos as i64));
-
-    std::iter::once(-1i64)
-        .chain(at_positions)
-        .chain(std::iter::once(s.len() as i64))
-        .tuples()
-        .fold(String::new(), |mut acc, (a, b)| {
-            let a = (a + 1) as usize;
-            acc.push_str(&s[a..b as usize]);
-            acc
-        })
-}
@@ -0,0 +1,127 @@
+//! A mutable transducer, for building transducers state by state, and arc by
+//! arc. Wraps the C++ `HfstBasicTransducer`.
+//!
+//! # Example
+//! ```no_run
+//! use hfst::{HfstBasicTransducer, ImplementationType};
+//!
+//! // Build a transducer that maps "ab" to "AB"
+//! let mut basic = HfstBasicTransducer::new();
+//! let s1 = basic.add_state();
+//! let s2 = basic.add_state();
+//! basic
+//!     .add_transition(HfstBasicTransducer::INITIAL_STATE, s1, "a", "A", 0.0)
+//!     .expect("symbols have no NUL bytes");
+//! basic
+//!     .add_transition(s1, s2, "b", "B", 0.0)
+//!     .expect("symbols have no NUL bytes");
+//! basic.set_final_weight(s2, 0.0);
+//!
+//! let transducer = basic
+//!     .to_transducer(ImplementationType::OpenFstTropical)
+//!     .expect("conversion did not fail");
+//! ```
+
+use std::ffi::c_int;
+use std::os::raw::c_void;
+
+use crate::{HfstTransducer, HfstTransducerError, ImplementationType, str_to_c};
+
+/// A state in an [`HfstBasicTransducer`].
+pub type HfstState = u32;
+
+/// A mutable transducer. Wraps the C++ HfstBasicTransducer.
+///
+/// Unlike [`HfstTransducer`], which is only modified through whole-transducer
+/// operations, this one is built by adding states and arcs one by one. When
+/// done, convert it to an [`HfstTransducer`] with
+/// [`HfstBasicTransducer::to_transducer`].
+pub struct HfstBasicTransducer {
+    // Opaque pointer to a C++ HfstBasicTransducer
+    inner: *mut c_void,
+}
+
+/// SAFETY: Same as for [`HfstTransducer`]: It can be moved between threads,
+/// but is *not* thread-safe.
+unsafe impl Send for HfstBasicTransducer {}
+
+impl HfstBasicTransducer {
+    /// The initial state. It always exists, also in a new, empty transducer.
+    pub const INITIAL_STATE: HfstState = 0;
+
+    /// Create a new transducer with only the (non-final) initial state.
+    pub fn new() -> Self {
+        let inner = unsafe { hfst_sys::hfst_basic_transducer_new() };
+        assert!(!inner.is_null());
+        Self { inner }
+    }
+
+    /// Add a new state, and return it.
+    pub fn add_state(&mut self) -> HfstState {
+        unsafe { hfst_sys::hfst_basic_transducer_add_state(self.inner) }
+    }
+
+    /// Add an arc from `source` to `target`, reading `input`, writing
+    /// `output`, with the given `weight`. States that do not exist yet are
+    /// created. Multichar symbols, such as `+N`, are given as-is. Fails if
+    /// a symbol contains a NUL byte.
+    pub fn add_transition(
+        &mut self,
+        source: HfstState,
+        target: HfstState,
+        input: &str,
+        output: &str,
+        weight: f32,
+    ) -> Result<(), HfstTransducerError> {
+        let input = str_to_c(input)?;
+        let output = str_to_c(output)?;
+        unsafe {
+            hfst_sys::hfst_basic_transducer_add_transition(
+                self.inner,
+                source,
+                target,
+                input.as_ptr(),
+                output.as_ptr(),
+                weight,
+            );
+        }
+        Ok(())
+    }
+
+    /// Make `state` a final state, with the final weight `weight`.
+    pub fn set_final_weight(&mut self, state: HfstState, weight: f32) {
+        unsafe { hfst_sys::hfst_basic_transducer_set_final_weight(self.inner, state, weight) };
+    }
+
+    /// Convert this transducer to an [`HfstTransducer`] of the given
+    /// implementation type. This transducer is left untouched, and can be
+    /// modified further, and converted again.
+    pub fn to_transducer(
+        &self,
+        implementation_type: ImplementationType,
+    ) -> Result<HfstTransducer, HfstTransducerError> {
+        let mut err: c_int = 0;
+        let inner = unsafe {
+            hfst_sys::hfst_basic_transducer_to_transducer(
+                self.inner,
+                implementation_type.to_raw(),
+                &raw mut err,
+            )
+        };
+        HfstTransducerError::check(err)?;
+        assert!(!inner.is_null());
+        Ok(HfstTransducer::from_inner(inner))
+    }
+}
+
+impl Default for HfstBasicTransducer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for HfstBasicTransducer {
+    fn drop(&mut self) {
+        unsafe { hfst_sys::hfst_basic_transducer_free(self.inner) };
+    }
+}
@@ -0,0 +1,94 @@
+//! Tests that memory stays bounded over many load/lookup/drop cycles. They
+//! are slow, so they only run with the `leak-tests` feature:
+//! `cargo test --release --features leak-tests leak_tests`.
+//!
+//! The ownership rules they enforce: Every wrapper owns the libhfst object
+//! behind its pointer, and frees it when dropped.
+//!
+//! - [`HfstTransducer`] frees its transducer with `hfst_transducer_free`.
+//! - [`HfstInputStream`] and [`HfstOutputStream`] close their streams.
+//! - [`HfstLookup`](crate::HfstLookup) frees the lookup with
+//!   `hfst_lookup_free`, after its iterator is freed with
+//!   `hfst_lookup_iterator_free`. It borrows the transducer, which must
+//!   outlive it.
+//! - [`HfstPathsIterator`](crate::HfstPathsIterator) frees its paths.
+//! - Strings and arrays that libhfst returns are copied, and then freed with
+//!   `hfst_free`.
+
+use crate::{HfstInputStream, HfstOutputStream, HfstTransducer, ImplementationType};
+
+const PATH: &str = "/usr/share/giella/nob/analyser-gt-desc.hfstol";
+
+/// The resident set size of this process, in pages.
+fn resident_pages() -> usize {
+    let statm = std::fs::read_to_string("/proc/self/statm").expect("/proc/self/statm");
+    statm.split_whitespace().nth(1).unwrap().parse().unwrap()
+}
+
+/// Run `cycle` `n` times, and assert that the resident set size does not
+/// grow by more than 4 MiB (with 4 KiB pages) over them.
+fn assert_bounded(n: usize, mut cycle: impl FnMut()) {
+    // warm up, so that allocator caches are already at their peak
+    for _ in 0..n / 10 {
+        cycle();
+    }
+    let before = resident_pages();
+    for _ in 0..n {
+        cycle();
+    }
+    let after = resident_pages();
+    assert!(
+        after < before + 1024,
+        "RSS grew from {before} to {after} pages"
+    );
+}
+
+/// A small optimized-lookup transducer, as the bytes of a binary `.hfstol`
+/// file.
+fn transducer_bytes() -> Vec<u8> {
+    let path = std::env::temp_dir().join("hfst-rust-leak-tests.hfstol");
+    let mut transducer = HfstTransducer::from_regex("{katt}:{cat} | {hund}:{dog}").unwrap();
+    transducer.convert(ImplementationType::HfstOlw).unwrap();
+    HfstOutputStream::new(&path, ImplementationType::HfstOlw)
+        .unwrap()
+        .write(&transducer)
+        .unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    bytes
+}
+
+#[test]
+fn lookups_do_not_leak() {
+    let tr = HfstInputStream::new(PATH)
+        .unwrap()
+        .read_only_transducer()
+        .unwrap();
+    assert_bounded(500_000, || {
+        // half of the iterators are dropped before they are exhausted
+        tr.lookup("hus").unwrap().into_iter().next();
+        let _ = tr.lookup("hus").unwrap().into_iter().count();
+    });
+}
+
+#[test]
+fn loading_transducers_does_not_leak() {
+    let bytes = transducer_bytes();
+    assert_bounded(10_000, || {
+        let tr = HfstInputStream::from_reader(&bytes[..])
+            .unwrap()
+            .read_only_transducer()
+            .unwrap();
+        assert_eq!(tr.lookup("katt").unwrap().into_iter().count(), 1);
+    });
+}
+
+#[test]
+fn transducer_operations_do_not_leak() {
+    assert_bounded(10_000, || {
+        let mut tr = HfstTransducer::from_regex("{katt}:{cat}").unwrap();
+        let other = HfstTransducer::from_regex("{cat}:{kat}").unwrap();
+        tr.compose(&other).unwrap().minimize().unwrap();
+        assert_eq!(tr.extract_paths(None, None).unwrap().count(), 1);
+    });
+}
@@ -4,14 +4,37 @@
 //!
 //! This library is ergonomic wrappers around [hfst_sys](https://docs.rs/hfst-sys).
 
+mod basic_transducer;
+#[cfg(all(test, feature = "leak-tests"))]
+mod leak_tests;
+mod output_stream;
+mod sync_transducer;
 #[cfg(feature = "tokio-actors")]
 pub mod transducer_actor;
+#[cfg(feature = "zhfst")]
+pub mod zhfst;
+
+pub use basic_transducer::{HfstBasicTransducer, HfstState};
+pub use output_stream::HfstOutputStream;
+pub use sync_transducer::SyncTransducer;
 
 use hfst_sys;
-use std::ffi::{CString, c_float};
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::ffi::{CString, c_float, c_int};
+use std::io::{Read, Write};
+use std::marker::PhantomData;
 use std::os::raw::{c_char, c_void};
 use std::path::Path;
 use std::ptr::addr_of_mut;
+use std::time::Duration;
+use unicode_normalization::UnicodeNormalization;
+
+// A note on panics and the FFI boundary: libhfst is never handed Rust
+// callbacks, every call into it returns before any Rust code that could
+// panic runs again, so a panic never unwinds through C++ frames. User code
+// that runs in the middle of a lookup (a ResultFilter) is wrapped in
+// catch_unwind, and a panic in it is given as LookupError::Panicked.
 
 fn strlen(s: *const c_char) -> usize {
     let mut len = 0;
@@ -38,9 +61,195 @@ fn str_to_boxed_c_charptr(s: &str) -> Box<[c_char]> {
     v
 }
 
-fn c_charptr_to_string(s: *const c_char) -> String {
-    let len = strlen(s);
-    unsafe { String::from_raw_parts(s as *mut u8, len, len) }
+/// Copy `s` into a NUL-terminated C string. Fails if `s` itself contains a
+/// NUL byte, as libhfst would only see the part before it.
+fn str_to_c(s: &str) -> Result<Box<[c_char]>, HfstTransducerError> {
+    match s.find('\0') {
+        Some(position) => Err(HfstTransducerError::ContainsNul(position)),
+        None => Ok(str_to_boxed_c_charptr(s)),
+    }
+}
+
+/// Copy the lookup input `s` into a NUL-terminated C string. Fails if `s`
+/// itself contains a NUL byte, as libhfst would only see the part before it.
+fn lookup_input_to_c(s: &str) -> Result<Box<[c_char]>, LookupError> {
+    if let Some(position) = s.find('\0') {
+        return Err(LookupError::InvalidInput(format!(
+            "the input contains a NUL byte at byte {position}"
+        )));
+    }
+    Ok(str_to_boxed_c_charptr(s))
+}
+
+/// Remove the diacritics from the Latin letters in `s`, so that e.g. `áđđa`
+/// becomes `adda`. Both the combining diacritics (U+0300 to U+036F) of
+/// decomposed Latin letters (such as the acute in `á`), and the strokes and
+/// bars of letters that do not decompose (such as in `đ`, `ŧ`, `ǥ` and `ø`)
+/// are removed. The Sámi `ŋ` is folded to `n` as well, though it is a letter
+/// of its own, as that is how it is written when it can not be typed.
+/// Everything else, such as the marks of Cyrillic or Greek letters (`й`,
+/// `ά`), is left as-is. The result is in NFC.
+pub fn fold_diacritics(s: &str) -> String {
+    let mut on_latin_letter = false;
+    s.nfd()
+        .filter(|&c| {
+            if !unicode_normalization::char::is_combining_mark(c) {
+                on_latin_letter = is_latin_letter(c);
+                return true;
+            }
+            !(on_latin_letter && ('\u{300}'..='\u{36f}').contains(&c))
+        })
+        .map(|c| match c {
+            'đ' => 'd',
+            'Đ' => 'D',
+            'ŧ' => 't',
+            'Ŧ' => 'T',
+            'ǥ' => 'g',
+            'Ǥ' => 'G',
+            'ŋ' => 'n',
+            'Ŋ' => 'N',
+            'ø' => 'o',
+            'Ø' => 'O',
+            'ł' => 'l',
+            'Ł' => 'L',
+            'ħ' => 'h',
+            'Ħ' => 'H',
+            c => c,
+        })
+        .nfc()
+        .collect()
+}
+
+/// Whether `c` is a letter of the Latin script, going by the Unicode blocks.
+fn is_latin_letter(c: char) -> bool {
+    c.is_alphabetic()
+        && matches!(
+            c,
+            'A'..='Z'
+                | 'a'..='z'
+                | '\u{c0}'..='\u{24f}'
+                | '\u{1e00}'..='\u{1eff}'
+                | '\u{2c60}'..='\u{2c7f}'
+                | '\u{a720}'..='\u{a7ff}'
+        )
+}
+
+/// Make a c string of `path`, for handing it over to libhfst. On Unix, the
+/// path is passed on as the bytes it is, so that paths that are not valid
+/// UTF-8 work too. Elsewhere, libhfst opens files with the narrow (`char`)
+/// APIs, so the path must be valid Unicode, and is passed on as UTF-8.
+fn path_to_c_string(path: &Path) -> std::io::Result<CString> {
+    use std::io::{Error, ErrorKind};
+
+    #[cfg(unix)]
+    let bytes = {
+        use std::os::unix::ffi::OsStrExt;
+        path.as_os_str().as_bytes().to_vec()
+    };
+    #[cfg(not(unix))]
+    let bytes = path
+        .to_str()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path is not valid Unicode"))?
+        .as_bytes()
+        .to_vec();
+    CString::new(bytes).map_err(|_| Error::new(ErrorKind::InvalidInput, "path contains a NUL byte"))
+}
+
+/// Make an owned String by copying the bytes of the null terminated c string
+/// `s`. The c string is left untouched, and must be freed by its owner.
+fn c_charptr_to_owned_string(s: *const c_char) -> String {
+    let bytes = unsafe { std::slice::from_raw_parts(s as *const u8, strlen(s)) };
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Copy out a c string that libhfst handed over to us (such as an error
+/// message written to an out-parameter), and free it. Gives an empty string
+/// if `s` is null.
+fn take_c_string(s: *mut c_char) -> String {
+    String::from_utf8_lossy(&take_c_bytes(s)).into_owned()
+}
+
+/// Like [`take_c_string`], but gives the bytes of the c string as they are,
+/// without requiring them to be valid UTF-8. The memory is allocated by
+/// libhfst, so it must be freed by libhfst, and not by the Rust allocator.
+fn take_c_bytes(s: *mut c_char) -> Vec<u8> {
+    if s.is_null() {
+        return vec![];
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(s as *const u8, strlen(s)) }.to_vec();
+    unsafe { hfst_sys::hfst_free(s as *mut c_void) };
+    bytes
+}
+
+/// Copy out an array of `len` c strings that libhfst handed over to us, and
+/// free both the strings and the array.
+fn take_c_string_array(array: *mut *mut c_char, len: usize) -> Vec<String> {
+    if array.is_null() {
+        return vec![];
+    }
+    let strings = unsafe { std::slice::from_raw_parts(array, len) }
+        .iter()
+        .map(|&s| take_c_string(s))
+        .collect();
+    unsafe { hfst_sys::hfst_free(array as *mut c_void) };
+    strings
+}
+
+/// Returns true if `s` is a flag diacritic, such as `@P.CASE.NOM@`,
+/// `@R.CASE@` or `@D.NeedNoun.ON@`: An operator (one of `P`, `N`, `R`,
+/// `D`, `C` or `U`), a feature, and optionally a value, separated by dots,
+/// and surrounded by `@`.
+fn is_flag_diacritic(s: &str) -> bool {
+    let Some(inner) = s.strip_prefix('@').and_then(|s| s.strip_suffix('@')) else {
+        return false;
+    };
+    let mut parts = inner.split('.');
+    let operator_ok = matches!(parts.next(), Some("P" | "N" | "R" | "D" | "C" | "U"));
+    let feature_ok = parts.next().is_some_and(|feature| !feature.is_empty());
+    let value_ok = parts.next().is_none_or(|value| !value.is_empty());
+    operator_ok && feature_ok && value_ok && parts.next().is_none() && !inner.contains('@')
+}
+
+/// Split `s` into the string without its flag diacritics, and the flag
+/// diacritics themselves, in order.
+fn split_flags(s: &str) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(s.len());
+    let mut flags = vec![];
+    let mut rest = s;
+    while let Some(start) = rest.find('@') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        // the shortest "@...@" candidate starting here
+        let candidate = rest[1..].find('@').map(|end| &rest[..end + 2]);
+        match candidate {
+            Some(flag) if is_flag_diacritic(flag) => {
+                flags.push(flag.to_string());
+                rest = &rest[flag.len()..];
+            }
+            _ => {
+                out.push('@');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    (out, flags)
+}
+
+/// Remove all flag diacritics (such as `@D.NeedNoun.ON@`) from `s`.
+///
+/// Only proper flag diacritics are removed, so other uses of `@`, such as in
+/// an e-mail address, or a lone `@` symbol, are left alone.
+///
+/// ```
+/// assert_eq!(
+///     hfst::strip_flags("sko+N+Msc+Sg+Indef@D.CmpOnly.FALSE@@D.NeedNoun.ON@"),
+///     "sko+N+Msc+Sg+Indef",
+/// );
+/// assert_eq!(hfst::strip_flags("post@example.com"), "post@example.com");
+/// ```
+pub fn strip_flags(s: &str) -> String {
+    split_flags(s).0
 }
 
 /// A stream for reading binary HFST transducers. Often from a file.
@@ -54,6 +263,60 @@ pub struct HfstInputStream {
 pub struct HfstTransducer {
     // Opaque pointer to a C++ HfstTransducer
     inner: *mut c_void,
+    // Harmonize alphabets in binary operations? See `set_harmonize()`
+    harmonize: bool,
+    // Made from the transducer when needed, and thrown away when it is
+    // modified, see `inner_mut()`
+    derived: Derived,
+}
+
+/// Things that are made from a transducer, and are expensive enough to only
+/// make once. They are thrown away whenever the transducer is modified.
+#[derive(Default)]
+struct Derived {
+    input_alphabet: OnceCell<InputAlphabet>,
+    // An optimized-lookup copy of the transducer, for looking up in
+    // transducers of other types
+    #[cfg(feature = "convert-for-lookup")]
+    lookup_copy: OnceCell<Box<HfstTransducer>>,
+}
+
+/// The symbols that the input of a lookup is split into, see
+/// [`HfstTransducer::tokenize_input`].
+struct InputAlphabet {
+    symbols: HashSet<String>,
+    // The length of the longest symbol, in bytes
+    longest: usize,
+}
+
+impl InputAlphabet {
+    fn new(alphabet: Vec<String>) -> Self {
+        // the special symbols, such as @_EPSILON_SYMBOL_@, are never typed
+        let symbols: HashSet<String> = alphabet
+            .into_iter()
+            .filter(|symbol| !(symbol.starts_with("@_") && symbol.ends_with("_@")))
+            .collect();
+        let longest = symbols.iter().map(String::len).max().unwrap_or(0);
+        Self { symbols, longest }
+    }
+
+    /// Split `s` into symbols, lazily, and without copying them. At each
+    /// position, the longest symbol that matches is taken, or else the
+    /// character on its own.
+    fn tokenize<'s>(&self, s: &'s str) -> impl Iterator<Item = &'s str> {
+        let mut rest = s;
+        std::iter::from_fn(move || {
+            let c = rest.chars().next()?;
+            let len = (c.len_utf8()..=self.longest.min(rest.len()))
+                .rev()
+                .filter(|&len| rest.is_char_boundary(len))
+                .find(|&len| self.symbols.contains(&rest[..len]))
+                .unwrap_or(c.len_utf8());
+            let (symbol, tail) = rest.split_at(len);
+            rest = tail;
+            Some(symbol)
+        })
+    }
 }
 
 /// SAFETY: The transducer can move between threads. Nothing will go wrong
@@ -62,16 +325,36 @@ pub struct HfstTransducer {
 ///
 /// But, NOTE: HfstTransducer is *NOT* thread-safe: Two diffferent threads that holds
 /// a reference to it, can *not* call .e.g `lookup()` at the same time (it SIGSEGVs).
+/// Wrap it in a [`SyncTransducer`] to share it between threads.
 unsafe impl Send for HfstTransducer {}
 // WOULD NOT WORK:
 //unsafe impl Sync for HfstTransducer {}
 
+impl Clone for HfstTransducer {
+    /// Make a copy of the transducer, with libhfst.
+    fn clone(&self) -> Self {
+        let inner = unsafe { hfst_sys::hfst_transducer_copy(self.inner) };
+        assert!(!inner.is_null());
+        Self {
+            inner,
+            harmonize: self.harmonize,
+            derived: Derived::default(),
+        }
+    }
+}
+
+impl Drop for HfstTransducer {
+    fn drop(&mut self) {
+        unsafe { hfst_sys::hfst_transducer_free(self.inner) };
+    }
+}
+
 /// Errors related to HfstInputStreams.
 #[derive(Debug, thiserror::Error)]
 pub enum HfstInputStreamError {
-    /// File not found, cannot be opened, or libhfst doesn't think this file
-    /// is an hfst file. This variant corresponds to the
-    /// NotTransducerStreamException in the C++ API.
+    /// libhfst doesn't think this file is an hfst file. This variant
+    /// corresponds to the NotTransducerStreamException in the C++ API.
+    /// (Files that can not be opened at all give [`HfstInputStreamError::Io`].)
     #[error("Not a transducer stream")]
     NotTransducerStream,
     /// Stream is at End Of File. This variant corresponds to
@@ -80,7 +363,8 @@ pub enum HfstInputStreamError {
     Eof,
     /// Bad stream. Essentially a stream is Bad if any OS-level IO errors has
     /// occurred. This variant corresponds to `HfstInputStream::is_bad()` in
-    /// the C++ API.
+    /// the C++ API. Other errors from libhfst when opening a stream are also
+    /// reported as this.
     #[error("Bad input stream")]
     Bad,
     /// The stream is recognized as a type of FST, but the version of libhfst
@@ -89,39 +373,636 @@ pub enum HfstInputStreamError {
     /// `ImplementationTypeNotAvailableException` in the C++ API.
     #[error("Implementation type not available")]
     ImplementationTypeNotAvailable,
+    /// Opening or reading the file or the reader failed, such as when the
+    /// file does not exist.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file does not have the expected SHA-256 hash, in
+    /// [`HfstInputStream::new_verified`].
+    #[error("Checksum mismatch: expected sha256 {expected}, got {actual}")]
+    ChecksumMismatch {
+        /// The expected hash, in lowercase hex.
+        expected: String,
+        /// The actual hash of the file, in lowercase hex.
+        actual: String,
+    },
+}
+
+/// Errors from operations on [`HfstTransducer`]s.
+#[derive(Debug, thiserror::Error)]
+pub enum HfstTransducerError {
+    /// The two transducers of a binary operation do not have the same
+    /// implementation type. This variant corresponds to
+    /// `TransducerTypeMismatchException` in the C++ API.
+    #[error("Transducer type mismatch")]
+    TransducerTypeMismatch,
+    /// The version of libhfst that is in use has not been compiled with
+    /// support for the requested implementation type. This variant
+    /// corresponds to `ImplementationTypeNotAvailableException` in the C++
+    /// API.
+    #[error("Implementation type not available")]
+    ImplementationTypeNotAvailable,
+    /// The weighted transducer has no deterministic equivalent, so weighted
+    /// determinization would never terminate.
+    #[error("Transducer is not determinizable")]
+    NotDeterminizable,
+    /// The transducer is cyclic, and the operation would never terminate.
+    /// This variant corresponds to `TransducerIsCyclicException` in the
+    /// C++ API.
+    #[error("Transducer is cyclic")]
+    TransducerIsCyclic,
+    /// The text is not valid AT&T format. This variant corresponds to
+    /// `NotValidAttFormatException` in the C++ API.
+    #[error("Not valid AT&T format")]
+    NotValidAttFormat,
+    /// The regular expression could not be compiled. Contains the error
+    /// message from the compiler.
+    #[error("Could not compile regular expression: {0}")]
+    XreCompile(String),
+    /// The twolc rules could not be compiled. Contains the error message
+    /// from the compiler.
+    #[error("Could not compile twolc rules: {0}")]
+    TwolcCompile(String),
+    /// A string that was to be handed over to libhfst, such as a regular
+    /// expression or a symbol, contains a NUL byte (at the given byte
+    /// offset), which C strings can not hold.
+    #[error("String contains a NUL byte at byte {0}")]
+    ContainsNul(usize),
+    /// Reading or writing failed on the Rust side.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Any other exception thrown by libhfst during the operation.
+    #[error("Transducer operation failed")]
+    Other,
+}
+
+/// Errors from looking up in a transducer, and from reading the results.
+#[derive(Debug, thiserror::Error)]
+pub enum LookupError {
+    /// libhfst can not look up in transducers of this implementation type,
+    /// or it was not compiled with support for it.
+    #[error("Can not look up in a transducer of type {0:?}")]
+    UnsupportedTransducerType(Option<ImplementationType>),
+    /// libhfst failed to look up in the transducer, because it is not an
+    /// optimized-lookup transducer. Convert it first, with
+    /// [`HfstTransducer::convert`] (or `hfst-fst2fst -O`), or enable the
+    /// `convert-for-lookup` feature, to have a converted copy looked up in
+    /// instead.
+    #[error(
+        "Can not look up in a transducer of type {0:?}, convert it to \
+         an optimized-lookup transducer first"
+    )]
+    NotOptimizedForLookup(Option<ImplementationType>),
+    /// The input can not be looked up, because it contains a NUL byte.
+    /// libhfst takes the input as a C string, so it would silently be cut
+    /// off at the NUL.
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    /// libhfst failed to do the lookup.
+    #[error("Lookup failed: {0}")]
+    Hfst(#[from] HfstTransducerError),
+    /// Code that was called while reading the results, such as a
+    /// [`ResultFilter`], panicked. Contains the panic message.
+    #[error("Panicked while reading the lookup results: {0}")]
+    Panicked(String),
+    /// The output of a result is not valid UTF-8. The raw bytes can be had
+    /// from the contained error, or by iterating over
+    /// [`HfstLookup::into_bytes`] instead.
+    #[error("Lookup result is not valid UTF-8")]
+    InvalidUtf8(#[from] std::string::FromUtf8Error),
+    /// The input is longer than [`LookupOptions::max_input_length`].
+    #[error("Input is longer than the {max} symbols that are allowed")]
+    InputTooLong {
+        /// The length of the input, in symbols. The symbols are only
+        /// counted up to `max + 1`, so a longer input gives `max + 1`.
+        length: usize,
+        /// The maximum length that was allowed.
+        max: usize,
+    },
+}
+
+impl HfstTransducerError {
+    /// Turn the error code written by one of the hfst_sys transducer
+    /// operations into a `Result`. 0 means success.
+    fn check(err: c_int) -> Result<(), Self> {
+        match err as u32 {
+            0 => Ok(()),
+            hfst_sys::TRANSDUCER_TYPE_MISMATCH => Err(Self::TransducerTypeMismatch),
+            hfst_sys::IMPLEMENTATION_TYPE_NOT_AVAILABLE => {
+                Err(Self::ImplementationTypeNotAvailable)
+            }
+            hfst_sys::NOT_DETERMINIZABLE => Err(Self::NotDeterminizable),
+            hfst_sys::TRANSDUCER_IS_CYCLIC => Err(Self::TransducerIsCyclic),
+            hfst_sys::NOT_VALID_ATT_FORMAT => Err(Self::NotValidAttFormat),
+            _ => Err(Self::Other),
+        }
+    }
+}
+
+/// The direction to push weights in, in [`HfstTransducer::push_weights`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushDirection {
+    /// Push the weights towards the initial state.
+    ToInitialState,
+    /// Push the weights towards the final states.
+    ToFinalState,
+}
+
+/// The backend implementation of a transducer. This enum corresponds to
+/// `ImplementationType` in the C++ API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImplementationType {
+    /// SFST
+    Sfst,
+    /// OpenFst, with the tropical weight semiring
+    OpenFstTropical,
+    /// OpenFst, with the log weight semiring
+    OpenFstLog,
+    /// Foma
+    Foma,
+    /// Xerox XFSM
+    Xfsm,
+    /// HFST optimized-lookup, unweighted (`.hfstol`)
+    HfstOl,
+    /// HFST optimized-lookup, weighted (`.hfstol`)
+    HfstOlw,
+    /// Legacy HFST version 2
+    Hfst2,
+    /// No type specified
+    Unspecified,
+}
+
+impl ImplementationType {
+    /// Convert from the `*_TYPE` constants of hfst_sys. Returns [`None`] for
+    /// `ERROR_TYPE`, and other values we do not know about.
+    fn from_raw(raw: c_int) -> Option<Self> {
+        Some(match raw as u32 {
+            hfst_sys::SFST_TYPE => Self::Sfst,
+            hfst_sys::TROPICAL_OPENFST_TYPE => Self::OpenFstTropical,
+            hfst_sys::LOG_OPENFST_TYPE => Self::OpenFstLog,
+            hfst_sys::FOMA_TYPE => Self::Foma,
+            hfst_sys::XFSM_TYPE => Self::Xfsm,
+            hfst_sys::HFST_OL_TYPE => Self::HfstOl,
+            hfst_sys::HFST_OLW_TYPE => Self::HfstOlw,
+            hfst_sys::HFST2_TYPE => Self::Hfst2,
+            hfst_sys::UNSPECIFIED_TYPE => Self::Unspecified,
+            _ => return None,
+        })
+    }
+
+    /// Convert to the corresponding `*_TYPE` constant of hfst_sys.
+    fn to_raw(self) -> c_int {
+        let raw = match self {
+            Self::Sfst => hfst_sys::SFST_TYPE,
+            Self::OpenFstTropical => hfst_sys::TROPICAL_OPENFST_TYPE,
+            Self::OpenFstLog => hfst_sys::LOG_OPENFST_TYPE,
+            Self::Foma => hfst_sys::FOMA_TYPE,
+            Self::Xfsm => hfst_sys::XFSM_TYPE,
+            Self::HfstOl => hfst_sys::HFST_OL_TYPE,
+            Self::HfstOlw => hfst_sys::HFST_OLW_TYPE,
+            Self::Hfst2 => hfst_sys::HFST2_TYPE,
+            Self::Unspecified => hfst_sys::UNSPECIFIED_TYPE,
+        };
+        raw as c_int
+    }
+}
+
+/// Statistics about a transducer, as returned by [`HfstTransducer::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransducerStats {
+    /// Number of states
+    pub states: usize,
+    /// Number of arcs (transitions)
+    pub arcs: usize,
+    /// Number of final states
+    pub final_states: usize,
+    /// Is the transducer deterministic?
+    pub deterministic: bool,
+    /// Is the transducer minimal?
+    pub minimal: bool,
+    /// The backend implementation type of the transducer
+    pub implementation_type: Option<ImplementationType>,
+}
+
+/// The maximum number of spellings that
+/// [`HfstTransducer::lookup_ignoring_diacritics`] looks up.
+pub const MAX_DIACRITIC_VARIANTS: usize = 256;
+
+/// Options for [`HfstTransducer::lookup_with_options`]. The default options
+/// give the same lookup as [`HfstTransducer::lookup`].
+///
+/// ```no_run
+/// use hfst::LookupOptions;
+///
+/// let options = LookupOptions {
+///     max_results: Some(10),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone)]
+pub struct LookupOptions {
+    /// Stop the lookup after this many results. [`None`] means no limit.
+    /// This is passed down to libhfst, so the remaining results are never
+    /// even computed.
+    pub max_results: Option<usize>,
+    /// Stop the lookup when it has taken this long. The results found up
+    /// until then are still returned, and [`HfstLookup::timed_out`] tells if
+    /// the lookup was cut off. [`None`] means no time limit.
+    pub time_cutoff: Option<Duration>,
+    /// Obey the flag diacritics of the transducer (the default), or ignore
+    /// them, and also return the results that the flags would have rejected.
+    /// The latter is mostly useful for debugging transducers.
+    pub obey_flags: bool,
+    /// Remove the flag diacritics (such as `@D.NeedNoun.ON@`) from the
+    /// results, with [`strip_flags`].
+    pub strip_flags: bool,
+    /// Also return the output of each result as a vector of symbols, in
+    /// [`LookupResult::symbols`]. Multichar symbols, such as `+N`, are kept
+    /// intact, so they can be told apart from the characters `+` and `N`.
+    pub output_symbols: bool,
+    /// Return the results sorted by weight, lowest (best) first. The order
+    /// from libhfst is unspecified otherwise. Note that this means that all
+    /// results are read before the first one is returned.
+    pub sort_by_weight: bool,
+    /// Only return one result per output string, the one with the lowest
+    /// weight. Mostly useful together with
+    /// [`LookupOptions::strip_flags`], as many results often differ only in
+    /// their flag diacritics. Like `sort_by_weight`, this means that all
+    /// results are read before the first one is returned.
+    pub deduplicate: bool,
+    /// Before doing the lookup, check if the input would give infinitely
+    /// many results (which can happen with cyclic transducers, and would
+    /// make the lookup never finish). If so, no lookup is done, and
+    /// [`HfstLookup::infinitely_ambiguous`] tells why there were no results.
+    /// The check takes some time, so it is off by default.
+    pub check_infinitely_ambiguous: bool,
+    /// Also return the aligned input and output symbol pairs of each result,
+    /// in [`LookupResult::alignment`]. This tells which part of the input
+    /// corresponds to which part of the output.
+    pub alignment: bool,
+    /// Discard results with a weight larger than this. This is passed down to
+    /// libhfst, so that paths are abandoned as soon as they get too heavy.
+    /// [`None`] means no limit.
+    pub max_weight: Option<f32>,
+    /// Remove the diacritics from the input before the lookup, with
+    /// [`fold_diacritics`], so that e.g. `áđđa` is looked up as `adda`.
+    /// Useful with transducers that are compiled without diacritics, as for
+    /// search. To go the other way around, and find `áđđa` when looking up
+    /// `adda`, see [`HfstTransducer::lookup_ignoring_diacritics`].
+    pub fold_diacritics: bool,
+    /// Replace the spaces of the input with this symbol before the lookup,
+    /// such as `#` or `_`, to look up multi-word expressions (such as
+    /// `giitu eatnat`) in analysers that separate the words of them with a
+    /// word boundary symbol. The symbol must be in the alphabet of the
+    /// transducer, for it to be recognized as one symbol. [`None`], the
+    /// default, leaves the spaces as they are.
+    pub word_boundary: Option<String>,
+    /// Drop or rescore the results as they are read, without collecting
+    /// them first. See [`ResultFilter`]. The filter is applied before the
+    /// results are sorted and deduplicated, so it can be used to rerank
+    /// them together with [`LookupOptions::sort_by_weight`].
+    pub filter: Option<ResultFilter>,
+    /// Also return the path through the transducer of each result, in
+    /// [`LookupResult::trace`]. For debugging transducers.
+    pub trace: bool,
+    /// What to do with the symbols of the input that are not in the
+    /// alphabet of the transducer. See [`UnknownSymbols`].
+    pub unknown_symbols: UnknownSymbols,
+    /// Refuse to look up inputs that are longer than this many symbols, with
+    /// [`LookupError::InputTooLong`]. The length is that of the input as it
+    /// is looked up, after the changes that
+    /// [`LookupOptions::fold_diacritics`] and [`LookupOptions::word_boundary`]
+    /// make to it. Protects against very long inputs, which may make libhfst
+    /// allocate a lot of memory. [`None`], the default, means no limit.
+    pub max_input_length: Option<usize>,
+}
+
+/// How a lookup treats the symbols of the input that are not in the
+/// alphabet of the transducer. Without this, it would depend on the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownSymbols {
+    /// The lookup gives no results (the default).
+    #[default]
+    Fail,
+    /// Unknown symbols are matched by the `@_IDENTITY_SYMBOL_@` arcs of the
+    /// transducer, which copy them to the output as-is, and by the
+    /// `@_UNKNOWN_SYMBOL_@` arcs, as transducers compiled from `?` in a
+    /// regular expression have.
+    Identity,
+}
+
+/// A closure that is called with the output and weight of each result of a
+/// lookup, and returns the new weight of the result, or [`None`] to drop it.
+/// If the closure panics, the lookup gives [`LookupError::Panicked`].
+///
+/// ```no_run
+/// use hfst::{LookupOptions, ResultFilter};
+///
+/// // Drop the results that are not nouns, and prefer the singular ones
+/// let options = LookupOptions {
+///     filter: Some(ResultFilter::new(|output, weight| {
+///         if !output.contains("+N") {
+///             None
+///         } else if output.contains("+Sg") {
+///             Some(weight - 1.0)
+///         } else {
+///             Some(weight)
+///         }
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct ResultFilter(std::sync::Arc<FilterFn>);
+
+type FilterFn = dyn Fn(&str, f32) -> Option<f32> + Send + Sync;
+
+impl ResultFilter {
+    /// Make a filter from the closure `f`.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&str, f32) -> Option<f32> + Send + Sync + 'static,
+    {
+        Self(std::sync::Arc::new(f))
+    }
+}
+
+impl std::fmt::Debug for ResultFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ResultFilter").finish_non_exhaustive()
+    }
+}
+
+impl Default for LookupOptions {
+    fn default() -> Self {
+        Self {
+            max_results: None,
+            time_cutoff: None,
+            obey_flags: true,
+            strip_flags: false,
+            output_symbols: false,
+            sort_by_weight: false,
+            deduplicate: false,
+            check_infinitely_ambiguous: false,
+            alignment: false,
+            max_weight: None,
+            fold_diacritics: false,
+            word_boundary: None,
+            filter: None,
+            trace: false,
+            unknown_symbols: UnknownSymbols::Fail,
+            max_input_length: None,
+        }
+    }
+}
+
+impl LookupOptions {
+    /// The input as it is handed over to libhfst, after the changes that
+    /// the options make to it.
+    fn prepare_input(&self, s: &str) -> String {
+        let mut input = if self.fold_diacritics {
+            fold_diacritics(s)
+        } else {
+            s.to_string()
+        };
+        if let Some(word_boundary) = &self.word_boundary {
+            input = input.replace(' ', word_boundary);
+        }
+        input
+    }
+
+    /// The options that are handed over to libhfst.
+    fn to_raw(&self) -> hfst_sys::HfstLookupOptions {
+        hfst_sys::HfstLookupOptions {
+            // -1 means no limit in the C++ API
+            max_results: self
+                .max_results
+                .map_or(-1, |n| n.min(isize::MAX as usize) as isize),
+            // 0.0 means no time limit in the C++ API
+            time_cutoff: self.time_cutoff.map_or(0.0, |d| d.as_secs_f64()),
+            obey_flags: self.obey_flags,
+            check_infinitely_ambiguous: self.check_infinitely_ambiguous,
+            alignment: self.alignment,
+            trace: self.trace,
+            unknown_symbols: match self.unknown_symbols {
+                UnknownSymbols::Fail => hfst_sys::UNKNOWN_SYMBOLS_FAIL,
+                UnknownSymbols::Identity => hfst_sys::UNKNOWN_SYMBOLS_IDENTITY,
+            } as c_int,
+            max_weight: self.max_weight.unwrap_or(f32::INFINITY),
+        }
+    }
+}
+
+#[cfg(feature = "gzip")]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+#[cfg(feature = "zstd")]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// A reader that calls `progress` with the number of bytes read so far, and
+/// `total`, on every read.
+struct ProgressReader<R, F> {
+    inner: R,
+    read: u64,
+    total: u64,
+    progress: F,
+}
+
+impl<R: Read, F: FnMut(u64, u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        (self.progress)(self.read, self.total);
+        Ok(n)
+    }
 }
 
 impl HfstInputStream {
     /// Load a file as an HfstInputStream.
+    ///
+    /// With the `gzip` or `zstd` features, files compressed with gzip (such
+    /// as `.hfst.gz`) or zstd (such as `.hfst.zst`) are recognized, and
+    /// decompressed into memory on the fly.
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, HfstInputStreamError> {
         use HfstInputStreamError as Error;
-        // this is apparently wrong and/or suboptimal, but going from
-        // a Path to a C char* is apparently not straight forward
-        let path = CString::new(format!("{}", path.as_ref().display())).unwrap();
-        let path = path.as_ptr() as *const c_char;
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        if let Some(stream) = Self::open_compressed(path.as_ref())? {
+            return Ok(stream);
+        }
+        let c_path = path_to_c_string(path.as_ref())?;
 
-        //let mut err: c_int = 0;
-        unsafe {
-            let stream = hfst_sys::hfst_input_stream(path);
-            if !stream.is_null() {
-                Ok(Self { inner: stream })
-            } else {
-                // TODO: Use better error handling. Probably this will be sending a
-                // pointer to an int to hfst_input_stream(), where it can write the
-                // error code.
-                Err(Error::NotTransducerStream)
-                //match err as u32 {
-                //    hfst_sys::NOT_TRANSDUCER_STREAM => {
-                //        Err(Error::NotTransducerStream)
-                //    }
-                //    hfst_sys::END_OF_STREAM => { Err(Error::Eof) }
-                //    hfst_sys::IMPLEMENTATION_TYPE_NOT_AVAILABLE => {
-                //        Err(Error::ImplementationTypeNotAvailable)
-                //    }
-                //    _ => unreachable!("all possible err values covered")
-                //}
+        let mut err: c_int = 0;
+        let stream = unsafe { hfst_sys::hfst_input_stream(c_path.as_ptr(), &raw mut err) };
+        if !stream.is_null() {
+            return Ok(Self { inner: stream });
+        }
+        match err as u32 {
+            hfst_sys::NOT_TRANSDUCER_STREAM => {
+                // libhfst does not tell if it could not open the file at all,
+                // or if it is not a transducer file, but we can find out
+                match std::fs::File::open(path) {
+                    Err(e) => Err(Error::Io(e)),
+                    Ok(_) => Err(Error::NotTransducerStream),
+                }
+            }
+            hfst_sys::END_OF_STREAM => Err(Error::Eof),
+            hfst_sys::IMPLEMENTATION_TYPE_NOT_AVAILABLE => {
+                Err(Error::ImplementationTypeNotAvailable)
             }
+            _ => Err(Error::Bad),
+        }
+    }
+
+    /// Open the file at `path` as an HfstInputStream, if it is compressed in
+    /// one of the formats that are enabled by features. Gives [`None`] for
+    /// uncompressed files, and files that can not be read, to leave them to
+    /// libhfst.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn open_compressed(path: &Path) -> Result<Option<Self>, HfstInputStreamError> {
+        use std::io::Seek;
+
+        let Ok(mut file) = std::fs::File::open(path) else {
+            return Ok(None);
+        };
+        // recognize the formats by their magic bytes, not the file names
+        let mut magic = [0u8; 4];
+        if file.read_exact(&mut magic).is_err() {
+            return Ok(None);
+        }
+        file.rewind()?;
+        #[cfg(feature = "gzip")]
+        if magic.starts_with(&GZIP_MAGIC) {
+            let decoder = flate2::read::MultiGzDecoder::new(std::io::BufReader::new(file));
+            return Self::from_reader(decoder).map(Some);
+        }
+        #[cfg(feature = "zstd")]
+        if magic.starts_with(&ZSTD_MAGIC) {
+            let decoder = zstd::stream::read::Decoder::new(file)?;
+            return Self::from_reader(decoder).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Load a file as an HfstInputStream, like [`HfstInputStream::new`], and
+    /// call `progress` with the number of bytes read so far, and the size of
+    /// the file, as it is read. For showing a progress bar when loading large
+    /// analysers. For compressed files, the bytes are the compressed ones.
+    ///
+    /// The file is read into memory, and libhfst makes its own copy of it
+    /// from there, so it takes twice the size of the file while loading.
+    pub fn new_with_progress<P, F>(path: P, progress: F) -> Result<Self, HfstInputStreamError>
+    where
+        P: AsRef<Path>,
+        F: FnMut(u64, u64),
+    {
+        let file = std::fs::File::open(path)?;
+        let total = file.metadata()?.len();
+        let mut reader = ProgressReader {
+            inner: file,
+            read: 0,
+            total,
+            progress,
+        };
+        let mut data = Vec::with_capacity(total as usize);
+        reader.read_to_end(&mut data)?;
+        Self::from_data(data)
+    }
+
+    /// Load a file as an HfstInputStream, like [`HfstInputStream::new`], but
+    /// first check that the SHA-256 hash of the file is `sha256` (in hex), to
+    /// detect corrupted or tampered files, instead of handing them over to
+    /// libhfst. For compressed files, it is the hash of the compressed file.
+    ///
+    /// The file is read into memory once, hashed there, and libhfst copies
+    /// the very same bytes, so that the file can not change between the
+    /// check and the load.
+    #[cfg(feature = "sha256")]
+    pub fn new_verified<P: AsRef<Path>>(
+        path: P,
+        sha256: &str,
+    ) -> Result<Self, HfstInputStreamError> {
+        use sha2::{Digest, Sha256};
+
+        let data = std::fs::read(path)?;
+        let actual: String = Sha256::digest(&data)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        if !actual.eq_ignore_ascii_case(sha256.trim()) {
+            return Err(HfstInputStreamError::ChecksumMismatch {
+                expected: sha256.trim().to_lowercase(),
+                actual,
+            });
+        }
+        Self::from_data(data)
+    }
+
+    /// Make an HfstInputStream of `data`, like
+    /// [`HfstInputStream::from_memory`], but decompress it first, if it is
+    /// compressed in one of the formats that are enabled by features.
+    fn from_data(data: Vec<u8>) -> Result<Self, HfstInputStreamError> {
+        // recognize the formats by their magic bytes, not the file names
+        #[cfg(feature = "gzip")]
+        if data.starts_with(&GZIP_MAGIC) {
+            return Self::from_reader(flate2::read::MultiGzDecoder::new(&data[..]));
         }
+        #[cfg(feature = "zstd")]
+        if data.starts_with(&ZSTD_MAGIC) {
+            return Self::from_reader(zstd::stream::read::Decoder::new(&data[..])?);
+        }
+        Self::from_memory(&data)
+    }
+
+    /// Make an HfstInputStream of what `reader` gives, such as a file in an
+    /// archive, a socket, or a transducer embedded with `include_bytes!`.
+    /// Everything is read into memory first, and then copied to libhfst.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, HfstInputStreamError> {
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        Self::from_memory(&data)
+    }
+
+    /// Make an HfstInputStream of the transducers in `data`. libhfst makes
+    /// its own copy of `data`, so it does not have to outlive the stream.
+    fn from_memory(data: &[u8]) -> Result<Self, HfstInputStreamError> {
+        let stream = unsafe {
+            hfst_sys::hfst_input_stream_from_memory(data.as_ptr() as *const c_char, data.len())
+        };
+        if stream.is_null() {
+            return Err(HfstInputStreamError::NotTransducerStream);
+        }
+        Ok(Self { inner: stream })
+    }
+
+    /// Load a file as an HfstInputStream, by memory-mapping it, instead of
+    /// reading it into a buffer of our own first, so that a large analyser is
+    /// not held in memory twice while loading. libhfst still makes its own
+    /// copy of the data while this runs, so the mapping is gone again when
+    /// this returns, and the stream does not share the page cache with other
+    /// processes that load the same file.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other
+    /// process, while this function runs. The mapped memory would change
+    /// while it is copied, which is undefined behaviour, and reading past the
+    /// end of a truncated file kills the process with `SIGBUS`. After this
+    /// returns, the stream no longer uses the file.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_mmap<P: AsRef<Path>>(path: P) -> Result<Self, HfstInputStreamError> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: The caller promises that the file is not modified while
+        // this runs, see above, and the mapping is dropped before returning.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Self::from_memory(&mmap)
+    }
+
+    /// The implementation type of the next transducer in this stream, without
+    /// reading it, or [`None`] if libhfst reports a type we do not know
+    /// about. Use it to check that a file really is an optimized-lookup
+    /// transducer ([`ImplementationType::HfstOl`] or
+    /// [`ImplementationType::HfstOlw`]) before doing lookups in it.
+    pub fn implementation_type(&self) -> Option<ImplementationType> {
+        ImplementationType::from_raw(unsafe { hfst_sys::hfst_input_stream_type(self.inner) })
     }
 
     /// Read the transducers from this HfstInputStream.
@@ -136,7 +1017,7 @@ impl HfstInputStream {
             if tr.is_null() {
                 return None;
             }
-            return Some(HfstTransducer { inner: tr });
+            return Some(HfstTransducer::from_inner(tr));
         })
         //let mut transducers = vec![];
         //loop {
@@ -157,6 +1038,17 @@ impl HfstInputStream {
         //transducers
     }
 
+    /// Read the transducers from this HfstInputStream, together with the
+    /// names that are stored in their headers, so that the transducers of a
+    /// file with several of them (such as a tokenizer and an analyser) can
+    /// be told apart. The name is [`None`] for transducers without one.
+    pub fn read_named_transducers(&self) -> impl Iterator<Item = (Option<String>, HfstTransducer)> {
+        self.read_transducers().map(|transducer| {
+            let name = transducer.name();
+            ((!name.is_empty()).then_some(name), transducer)
+        })
+    }
+
     /// Return the *one* transducer that exists in this `HfstInputStream` as
     /// [`Some(transducer)`], or return [`None`] if there are no transducers, or
     /// more than one.
@@ -171,124 +1063,3035 @@ impl HfstInputStream {
 }
 
 impl HfstTransducer {
-    /// Look up the string `s` in this `Transducer`.
-    pub fn lookup(&self, s: &str) -> HfstLookup {
-        let sp = str_to_boxed_c_charptr(s);
-        assert_eq!(strlen(sp.as_ptr()), s.len());
-        let handle = unsafe { hfst_sys::hfst_lookup(self.inner, sp.as_ptr()) };
-        assert!(!handle.is_null());
-        HfstLookup { handle }
+    /// Wrap the pointer to a C++ HfstTransducer, that we now own.
+    fn from_inner(inner: *mut c_void) -> Self {
+        Self {
+            inner,
+            harmonize: true,
+            derived: Derived::default(),
+        }
     }
-}
 
-impl Drop for HfstInputStream {
-    fn drop(&mut self) {
-        unsafe {
-            hfst_sys::hfst_input_stream_close(self.inner);
-            //hfst_sys::hfst_input_stream_free(self.inner);
+    /// The pointer to the transducer, for modifying it. Whatever was made
+    /// from the transducer before is thrown away, as it may no longer match.
+    fn inner_mut(&mut self) -> *mut c_void {
+        self.derived = Derived::default();
+        self.inner
+    }
+
+    /// The alphabet that inputs are tokenized with. It is made the first
+    /// time it is needed.
+    fn input_alphabet(&self) -> &InputAlphabet {
+        self.derived
+            .input_alphabet
+            .get_or_init(|| InputAlphabet::new(self.alphabet()))
+    }
+
+    /// Load the one transducer in the file at `path`, like
+    /// [`HfstInputStream::read_only_transducer`] does, but without blocking
+    /// the tokio runtime: The file is read on a blocking thread, with
+    /// [`tokio::task::spawn_blocking`]. Loading a large analyser can take
+    /// seconds, so do this when loading at startup of a service.
+    #[cfg(feature = "tokio")]
+    pub async fn load_async<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Option<HfstTransducer>, HfstInputStreamError> {
+        let path = path.as_ref().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let input_stream = HfstInputStream::new(path)?;
+            Ok(input_stream.read_only_transducer())
+        })
+        .await
+        .expect("loading did not panic")
+    }
+
+    /// Create a transducer that maps the string `input` to the string
+    /// `output`, with the given `weight`. The strings are split into
+    /// symbols by the default libhfst tokenizer, i.e. into characters.
+    ///
+    /// The transducer is of type [`ImplementationType::OpenFstTropical`].
+    pub fn from_pair(
+        input: &str,
+        output: &str,
+        weight: f32,
+    ) -> Result<HfstTransducer, HfstTransducerError> {
+        Self::from_pairs([(input, output, weight)])
+    }
+
+    /// Create a transducer that maps each of the `input` strings to its
+    /// corresponding `output` string, with the given weight. This is the
+    /// disjunction of [`HfstTransducer::from_pair`] for each of the pairs,
+    /// but built in one go.
+    ///
+    /// The transducer is of type [`ImplementationType::OpenFstTropical`].
+    pub fn from_pairs<'a, I>(pairs: I) -> Result<HfstTransducer, HfstTransducerError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str, f32)>,
+    {
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        let mut weights = vec![];
+        for (input, output, weight) in pairs {
+            inputs.push(str_to_c(input)?);
+            outputs.push(str_to_c(output)?);
+            weights.push(weight);
         }
+        let input_ptrs: Vec<*const c_char> = inputs.iter().map(|s| s.as_ptr()).collect();
+        let output_ptrs: Vec<*const c_char> = outputs.iter().map(|s| s.as_ptr()).collect();
+
+        let mut err: c_int = 0;
+        let inner = unsafe {
+            hfst_sys::hfst_transducer_from_string_pairs(
+                input_ptrs.as_ptr(),
+                output_ptrs.as_ptr(),
+                weights.as_ptr(),
+                weights.len(),
+                ImplementationType::OpenFstTropical.to_raw(),
+                &raw mut err,
+            )
+        };
+        HfstTransducerError::check(err)?;
+        assert!(!inner.is_null());
+        Ok(HfstTransducer::from_inner(inner))
     }
-}
 
-/// Represents a handle to a lookup in progress. This structure is returned
-/// from [`HfstTransducer::lookup`]. This type implements [`IntoIterator`],
-/// to iterate over the results in the lookup.
-pub struct HfstLookup {
-    handle: *mut c_void,
-}
+    /// Read a transducer in AT&T tabular text format (as written by e.g.
+    /// `hfst-fst2txt`, or OpenFst's `fstprint`) from `reader`. Epsilons are
+    /// written as `@0@` or `@_EPSILON_SYMBOL_@`.
+    ///
+    /// The transducer is of type [`ImplementationType::OpenFstTropical`].
+    pub fn from_att_reader<R: Read>(mut reader: R) -> Result<HfstTransducer, HfstTransducerError> {
+        let mut text = vec![];
+        reader.read_to_end(&mut text)?;
+        let epsilon = str_to_boxed_c_charptr("@0@");
 
-impl IntoIterator for HfstLookup {
-    type Item = (String, f32);
-    type IntoIter = HfstLookupIterator;
+        let mut err: c_int = 0;
+        let inner = unsafe {
+            hfst_sys::hfst_transducer_from_att(
+                text.as_ptr() as *const c_char,
+                text.len(),
+                epsilon.as_ptr(),
+                ImplementationType::OpenFstTropical.to_raw(),
+                &raw mut err,
+            )
+        };
+        HfstTransducerError::check(err)?;
+        assert!(!inner.is_null());
+        Ok(HfstTransducer::from_inner(inner))
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        let inner = unsafe { hfst_sys::hfst_lookup_iterator(self.handle) };
+    /// Compile the xerox-style regular expression `regex` into a transducer,
+    /// the same way `hfst-regexp2fst` does.
+    ///
+    /// ```no_run
+    /// let transducer = hfst::HfstTransducer::from_regex("a:b (c)+").unwrap();
+    /// ```
+    ///
+    /// The transducer is of type [`ImplementationType::OpenFstTropical`].
+    pub fn from_regex(regex: &str) -> Result<HfstTransducer, HfstTransducerError> {
+        let regex = str_to_c(regex)?;
+        let mut error_message: *mut c_char = std::ptr::null_mut();
+        let mut err: c_int = 0;
+        let inner = unsafe {
+            hfst_sys::hfst_xre_compile(
+                regex.as_ptr(),
+                ImplementationType::OpenFstTropical.to_raw(),
+                &raw mut error_message,
+                &raw mut err,
+            )
+        };
+        if err as u32 == hfst_sys::XRE_COMPILE_ERROR {
+            let message = take_c_string(error_message);
+            return Err(HfstTransducerError::XreCompile(message));
+        }
+        HfstTransducerError::check(err)?;
+        assert!(!inner.is_null());
+        Ok(HfstTransducer::from_inner(inner))
+    }
+
+    /// Compile the two-level rules (twolc source, as in a `.twolc` file) read
+    /// from `reader` into rule transducers, one per rule, the same way
+    /// `hfst-twolc` does. The rules are typically applied to a lexicon with
+    /// [`HfstTransducer::compose_intersect`].
+    ///
+    /// The transducers are of type [`ImplementationType::OpenFstTropical`].
+    pub fn from_twolc_reader<R: Read>(
+        mut reader: R,
+    ) -> Result<Vec<HfstTransducer>, HfstTransducerError> {
+        let mut text = vec![];
+        reader.read_to_end(&mut text)?;
 
-        HfstLookupIterator { inner }
+        let mut n_rules: usize = 0;
+        let mut error_message: *mut c_char = std::ptr::null_mut();
+        let mut err: c_int = 0;
+        let rules = unsafe {
+            hfst_sys::hfst_twolc_compile(
+                text.as_ptr() as *const c_char,
+                text.len(),
+                ImplementationType::OpenFstTropical.to_raw(),
+                &raw mut n_rules,
+                &raw mut error_message,
+                &raw mut err,
+            )
+        };
+        if err as u32 == hfst_sys::TWOLC_COMPILE_ERROR {
+            let message = take_c_string(error_message);
+            return Err(HfstTransducerError::TwolcCompile(message));
+        }
+        HfstTransducerError::check(err)?;
+        assert!(!rules.is_null());
+        // we take ownership of each of the transducers, but the array holding
+        // them is ours to free
+        let transducers = unsafe { std::slice::from_raw_parts(rules, n_rules) }
+            .iter()
+            .map(|&inner| HfstTransducer::from_inner(inner))
+            .collect();
+        unsafe { hfst_sys::hfst_free(rules as *mut c_void) };
+        Ok(transducers)
     }
-}
 
-pub struct HfstLookupIterator {
-    // the underlying HfstLooup
-    //lookup_handle: HfstLookup,
-    // Opaque pointer to a "struct ResultIterator"
-    inner: *mut hfst_sys::ResultIterator,
-}
+    /// Look up the string `s` in this `Transducer`. The string is matched
+    /// against the input side, and the results are read off the output side
+    /// (*apply down*), so for an analyser, this gives the analyses of `s`.
+    ///
+    /// Fails if libhfst can not look up in this transducer, or if the input
+    /// contains a NUL byte.
+    pub fn lookup(&self, s: &str) -> Result<HfstLookup<'_>, LookupError> {
+        let sp = lookup_input_to_c(s)?;
+        self.start_lookup(LookupOptions::default(), |transducer, err| unsafe {
+            hfst_sys::hfst_lookup(transducer, sp.as_ptr(), err)
+        })
+    }
 
-impl Iterator for HfstLookupIterator {
-    /// The type of the elements being iterated over. In the lookup case,
-    /// the full string, as well as a weight.
-    type Item = (String, f32);
+    /// Turn the `handle` returned by one of the hfst_sys lookup functions,
+    /// and the error code `err` it wrote, into a `Result`.
+    fn check_lookup(&self, handle: *mut c_void, err: c_int) -> Result<(), LookupError> {
+        match err as u32 {
+            hfst_sys::IMPLEMENTATION_TYPE_NOT_AVAILABLE | hfst_sys::TRANSDUCER_TYPE_MISMATCH => {
+                return Err(LookupError::UnsupportedTransducerType(
+                    self.implementation_type(),
+                ));
+            }
+            hfst_sys::NOT_OPTIMIZED_FOR_LOOKUP => {
+                return Err(LookupError::NotOptimizedForLookup(
+                    self.implementation_type(),
+                ));
+            }
+            _ => HfstTransducerError::check(err)?,
+        }
+        if handle.is_null() {
+            return Err(HfstTransducerError::Other.into());
+        }
+        Ok(())
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if unsafe { hfst_sys::hfst_lookup_iterator_done(self.inner) } {
-            None
-        } else {
-            let mut s: *mut c_char = std::ptr::null_mut();
-            let w: c_float = 0.0;
-            unsafe {
-                hfst_sys::hfst_lookup_iterator_value(
-                    self.inner,
-                    addr_of_mut!(s),
-                    &w as *const _ as *mut _,
-                );
+    /// Start a lookup (in the *apply down* direction): `start` calls one of
+    /// the hfst_sys lookup functions on the transducer it is given, and
+    /// returns the handle. If the lookup fails because this is not an
+    /// optimized-lookup transducer, it is retried in a converted copy of it
+    /// with the `convert-for-lookup` feature, or fails with
+    /// [`LookupError::NotOptimizedForLookup`]. Any other error is returned
+    /// as it is.
+    fn start_lookup(
+        &self,
+        options: LookupOptions,
+        start: impl Fn(*mut c_void, *mut c_int) -> *mut c_void,
+    ) -> Result<HfstLookup<'_>, LookupError> {
+        let mut err: c_int = 0;
+        let handle = start(self.inner, &raw mut err);
+        match self.check_lookup(handle, err) {
+            Ok(()) => Ok(HfstLookup {
+                handle,
+                options,
+                transducer: PhantomData,
+            }),
+            Err(LookupError::NotOptimizedForLookup(_)) => {
+                self.start_converted_lookup(options, start)
             }
-            let rust_string = c_charptr_to_string(s);
-            unsafe { hfst_sys::hfst_lookup_iterator_next(self.inner) };
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(not(feature = "convert-for-lookup"))]
+    fn start_converted_lookup(
+        &self,
+        _options: LookupOptions,
+        _start: impl Fn(*mut c_void, *mut c_int) -> *mut c_void,
+    ) -> Result<HfstLookup<'_>, LookupError> {
+        Err(LookupError::NotOptimizedForLookup(
+            self.implementation_type(),
+        ))
+    }
+
+    /// Do the lookup of [`HfstTransducer::start_lookup`] in a copy of this
+    /// transducer that is converted to an optimized-lookup transducer. The
+    /// copy is made on the first lookup, which is slow for large
+    /// transducers, and then kept for the later lookups, until this
+    /// transducer is modified.
+    #[cfg(feature = "convert-for-lookup")]
+    fn start_converted_lookup(
+        &self,
+        options: LookupOptions,
+        start: impl Fn(*mut c_void, *mut c_int) -> *mut c_void,
+    ) -> Result<HfstLookup<'_>, LookupError> {
+        let converted = self.lookup_copy()?;
+        let mut err: c_int = 0;
+        let handle = start(converted.inner, &raw mut err);
+        converted.check_lookup(handle, err)?;
+        // The copy is only thrown away through `&mut self`, so it outlives
+        // the lookup, which borrows `self`
+        Ok(HfstLookup {
+            handle,
+            options,
+            transducer: PhantomData,
+        })
+    }
 
-            // c_float is always rust f32, right?
-            Some((rust_string, w))
+    /// The optimized-lookup copy of this transducer, see
+    /// [`HfstTransducer::start_converted_lookup`]. A failed conversion is
+    /// not kept, so it is tried again on the next lookup.
+    #[cfg(feature = "convert-for-lookup")]
+    fn lookup_copy(&self) -> Result<&HfstTransducer, HfstTransducerError> {
+        if let Some(copy) = self.derived.lookup_copy.get() {
+            return Ok(copy);
         }
+        let mut copy = self.clone();
+        copy.convert(ImplementationType::HfstOlw)?;
+        Ok(self.derived.lookup_copy.get_or_init(|| Box::new(copy)))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    const PATH: &'static str = "/usr/share/giella/nob/analyser-gt-desc.hfstol";
-    use super::*;
+    /// Look up the string `s` in this `Transducer`, like
+    /// [`HfstTransducer::lookup`], but with the given `options`. Also fails
+    /// if the options reject the input, see
+    /// [`LookupOptions::max_input_length`].
+    pub fn lookup_with_options(
+        &self,
+        s: &str,
+        options: &LookupOptions,
+    ) -> Result<HfstLookup<'_>, LookupError> {
+        let input = options.prepare_input(s);
+        if let Some(max) = options.max_input_length {
+            // a symbol is at least one character, so only tokenize the input
+            // when it could be too long, and only until it is
+            if input.chars().nth(max).is_some() {
+                let length = self
+                    .input_alphabet()
+                    .tokenize(&input)
+                    .take(max.saturating_add(1))
+                    .count();
+                if length > max {
+                    return Err(LookupError::InputTooLong { length, max });
+                }
+            }
+        }
+        let sp = lookup_input_to_c(&input)?;
+        let raw_options = options.to_raw();
+        self.start_lookup(options.clone(), |transducer, err| unsafe {
+            hfst_sys::hfst_lookup_with_options(transducer, sp.as_ptr(), &raw const raw_options, err)
+        })
+    }
 
-    #[test]
-    fn can_open_inputstream() {
-        let input_stream = HfstInputStream::new(PATH);
-        assert!(input_stream.is_ok());
+    /// Look up the already tokenized `symbols` in this `Transducer`. Each
+    /// of the symbols is fed to the transducer as-is, so multichar symbols
+    /// such as `+Use/NG` are not split up by the input tokenizer.
+    pub fn lookup_symbols(&self, symbols: &[&str]) -> Result<HfstLookup<'_>, LookupError> {
+        self.lookup_symbols_with_options(symbols, &LookupOptions::default())
     }
 
-    #[test]
-    fn errors_on_opening_nonexistant() {
-        let input_stream = HfstInputStream::new("/this/path/doesnt/exist");
-        assert!(matches!(input_stream, Err(())));
+    /// Like [`HfstTransducer::lookup_symbols`], but with the given `options`.
+    pub fn lookup_symbols_with_options(
+        &self,
+        symbols: &[&str],
+        options: &LookupOptions,
+    ) -> Result<HfstLookup<'_>, LookupError> {
+        if let Some(max) = options.max_input_length
+            && symbols.len() > max
+        {
+            return Err(LookupError::InputTooLong {
+                length: symbols.len(),
+                max,
+            });
+        }
+        let symbols = symbols
+            .iter()
+            .map(|s| lookup_input_to_c(&options.prepare_input(s)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let symbol_ptrs: Vec<*const c_char> = symbols.iter().map(|s| s.as_ptr()).collect();
+        let raw_options = options.to_raw();
+        self.start_lookup(options.clone(), |transducer, err| unsafe {
+            hfst_sys::hfst_lookup_symbols(
+                transducer,
+                symbol_ptrs.as_ptr(),
+                symbol_ptrs.len(),
+                &raw const raw_options,
+                err,
+            )
+        })
     }
 
-    #[test]
-    fn can_lookup() {
-        let input_stream = HfstInputStream::new(PATH).unwrap();
-        let transducers = input_stream.read_transducers();
-        let transducer = transducers
-            .first()
-            .expect("the hfst input stream has at least one transducer");
-        let query = "sko";
-        let results = transducer.lookup(query);
-        let mut seen = std::collections::HashMap::new();
-        seen.insert(
-            "sko+N+Msc+Pl+Indef@D.CmpOnly.FALSE@@D.CmpPref.TRUE@@D.NeedNoun.ON@",
-            false,
-        );
-        seen.insert(
-            "sko+N+Msc+Pl+Nynorsk+Indef@D.CmpOnly.FALSE@@D.CmpPref.TRUE@@D.NeedNoun.ON@",
-            false,
-        );
-        seen.insert(
-            "sko+N+Msc+Sg+Indef@D.CmpOnly.FALSE@@D.CmpPref.TRUE@@D.NeedNoun.ON@",
-            false,
-        );
-        seen.insert("sko+V+Imp", false);
-        seen.insert("sko+V+Inf", false);
+    /// Look up each of the `inputs`, and collect all the results, one `Vec`
+    /// per input, in the same order as the inputs.
+    pub fn lookup_batch(&self, inputs: &[&str]) -> Result<Vec<Vec<LookupResult>>, LookupError> {
+        self.lookup_batch_with_options(inputs, &LookupOptions::default())
+    }
 
-        for (result, _weight) in results {
-            *seen.get_mut(result.as_str()).unwrap() = true;
+    /// Like [`HfstTransducer::lookup_batch`], but with the given `options`.
+    pub fn lookup_batch_with_options(
+        &self,
+        inputs: &[&str],
+        options: &LookupOptions,
+    ) -> Result<Vec<Vec<LookupResult>>, LookupError> {
+        inputs
+            .iter()
+            .map(|input| {
+                self.lookup_with_options(input, options)?
+                    .into_iter()
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Look up `s` like hfst-tokenise and hfst-proc does for capitalized
+    /// words: First try `s` as-is, then all lowercased, and then with only
+    /// the first letter lowercased. The results of the first of these that
+    /// gives any results are returned, together with which of them it was.
+    /// Returns [`None`] if none of them give any results.
+    pub fn lookup_cased(
+        &self,
+        s: &str,
+    ) -> Result<Option<(CaseVariant, Vec<LookupResult>)>, LookupError> {
+        self.lookup_cased_with_options(s, &LookupOptions::default())
+    }
+
+    /// Like [`HfstTransducer::lookup_cased`], but with the given `options`.
+    pub fn lookup_cased_with_options(
+        &self,
+        s: &str,
+        options: &LookupOptions,
+    ) -> Result<Option<(CaseVariant, Vec<LookupResult>)>, LookupError> {
+        let mut chars = s.chars();
+        let first_lowercased = match chars.next() {
+            Some(first) => first.to_lowercase().chain(chars).collect(),
+            None => String::new(),
+        };
+        let variants = [
+            (CaseVariant::Original, s.to_string()),
+            (CaseVariant::Lowercase, s.to_lowercase()),
+            (CaseVariant::FirstLowercase, first_lowercased),
+        ];
+
+        let mut tried: Vec<&str> = vec![];
+        for (variant, input) in &variants {
+            if tried.contains(&input.as_str()) {
+                continue;
+            }
+            tried.push(input);
+            let results: Vec<_> = self
+                .lookup_with_options(input, options)?
+                .into_iter()
+                .collect::<Result<_, _>>()?;
+            if !results.is_empty() {
+                return Ok(Some((*variant, results)));
+            }
         }
+        Ok(None)
+    }
 
-        assert!(seen.into_iter().all(|(_k, v)| v));
+    /// Look up `s` without regard to diacritics: The results of all the
+    /// strings that are equal to `s` after [`fold_diacritics`] are returned,
+    /// so that looking up `adda` also gives the results of `áđđa`. The
+    /// alternatives of each letter are taken from the alphabet of this
+    /// transducer, and each of the combinations is looked up in turn, so the
+    /// results are not sorted or deduplicated across the combinations.
+    ///
+    /// As the number of combinations grows quickly with the length of `s`,
+    /// at most [`MAX_DIACRITIC_VARIANTS`] of them are looked up.
+    pub fn lookup_ignoring_diacritics(&self, s: &str) -> Result<Vec<LookupResult>, LookupError> {
+        self.lookup_ignoring_diacritics_with_options(s, &LookupOptions::default())
+    }
+
+    /// Like [`HfstTransducer::lookup_ignoring_diacritics`], but with the
+    /// given `options`.
+    pub fn lookup_ignoring_diacritics_with_options(
+        &self,
+        s: &str,
+        options: &LookupOptions,
+    ) -> Result<Vec<LookupResult>, LookupError> {
+        let mut letters: std::collections::HashMap<String, Vec<String>> = Default::default();
+        for symbol in self.alphabet() {
+            if symbol.chars().count() == 1 {
+                letters
+                    .entry(fold_diacritics(&symbol))
+                    .or_default()
+                    .push(symbol);
+            }
+        }
+
+        let mut variants = vec![String::new()];
+        for c in fold_diacritics(s).chars() {
+            let c = c.to_string();
+            let alternatives = match letters.get(&c) {
+                Some(alternatives) => alternatives.as_slice(),
+                None => std::slice::from_ref(&c),
+            };
+            variants = variants
+                .iter()
+                .flat_map(|variant| {
+                    alternatives
+                        .iter()
+                        .map(move |alt| format!("{variant}{alt}"))
+                })
+                .take(MAX_DIACRITIC_VARIANTS)
+                .collect();
+        }
+
+        let mut results = vec![];
+        for variant in &variants {
+            for result in self.lookup_with_options(variant, options)? {
+                results.push(result?);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Returns true if looking up `s` in this transducer would give
+    /// infinitely many results, i.e. the lookup would never finish. Fails
+    /// with [`LookupError::InvalidInput`] if `s` contains a NUL byte.
+    pub fn is_lookup_infinitely_ambiguous(&self, s: &str) -> Result<bool, LookupError> {
+        let sp = lookup_input_to_c(s)?;
+        Ok(unsafe { hfst_sys::hfst_is_lookup_infinitely_ambiguous(self.inner, sp.as_ptr()) })
+    }
+
+    /// Find the longest prefix of `s` that this transducer accepts, and look
+    /// it up. Returns the number of characters (not bytes) of the prefix,
+    /// together with its results, or [`None`] if no non-empty prefix of `s`
+    /// is accepted. This is the building block for tokenizers and
+    /// segmenters.
+    pub fn lookup_prefix(
+        &self,
+        s: &str,
+    ) -> Result<Option<(usize, Vec<LookupResult>)>, LookupError> {
+        self.lookup_prefix_with_options(s, &LookupOptions::default())
+    }
+
+    /// Like [`HfstTransducer::lookup_prefix`], but with the given `options`.
+    pub fn lookup_prefix_with_options(
+        &self,
+        s: &str,
+        options: &LookupOptions,
+    ) -> Result<Option<(usize, Vec<LookupResult>)>, LookupError> {
+        if s.is_empty() {
+            return Ok(None);
+        }
+        let ends = s.char_indices().skip(1).map(|(i, _)| i).chain([s.len()]);
+        let prefixes: Vec<_> = ends
+            .enumerate()
+            .map(|(n, end)| (n + 1, &s[..end]))
+            .collect();
+        for (n_chars, prefix) in prefixes.into_iter().rev() {
+            let results: Vec<_> = self
+                .lookup_with_options(prefix, options)?
+                .into_iter()
+                .collect::<Result<_, _>>()?;
+            if !results.is_empty() {
+                return Ok(Some((n_chars, results)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Look up `s`, and return only the lowest weight (best) result, or
+    /// [`None`] if there are no results. The results are not collected or
+    /// sorted on the way.
+    pub fn lookup_best(&self, s: &str) -> Result<Option<LookupResult>, LookupError> {
+        let mut best: Option<LookupResult> = None;
+        for result in self.lookup(s)? {
+            let result = result?;
+            if best.as_ref().is_none_or(|best| result.weight < best.weight) {
+                best = Some(result);
+            }
+        }
+        Ok(best)
+    }
+
+    /// Look up `s` allowing for typos: Results are also given for the
+    /// strings that are at most `max_edits` insertions, deletions or
+    /// substitutions of single characters away from `s`. Each edit adds 1 to
+    /// the weight of the result. An output that can be reached with
+    /// different edits is only given once, with the lowest weight, and the
+    /// results are sorted by weight, so the exact matches come first.
+    ///
+    /// The query is composed with an edit distance transducer, which is built
+    /// from the single character symbols of the alphabet of this transducer,
+    /// and then with this transducer. It therefore does not work on
+    /// optimized-lookup transducers (`.hfstol`), which can not be composed,
+    /// and fails with [`LookupError::UnsupportedTransducerType`] for them.
+    /// Flag diacritics are not obeyed.
+    pub fn lookup_fuzzy(&self, s: &str, max_edits: u32) -> Result<Vec<LookupResult>, LookupError> {
+        const EPSILON: &str = "@_EPSILON_SYMBOL_@";
+        let implementation_type = match self.implementation_type() {
+            Some(ImplementationType::HfstOl | ImplementationType::HfstOlw) | None => {
+                return Err(LookupError::UnsupportedTransducerType(
+                    self.implementation_type(),
+                ));
+            }
+            Some(implementation_type) => implementation_type,
+        };
+
+        let mut query = HfstBasicTransducer::new();
+        let mut state = HfstBasicTransducer::INITIAL_STATE;
+        let mut buf = [0; 4];
+        for c in s.chars() {
+            let next = query.add_state();
+            let symbol = c.encode_utf8(&mut buf);
+            query.add_transition(state, next, symbol, symbol, 0.0)?;
+            state = next;
+        }
+        query.set_final_weight(state, 0.0);
+
+        // the characters of the input may not be in the alphabet, but they
+        // must still be possible to delete or substitute
+        let mut targets: Vec<String> = self
+            .alphabet()
+            .into_iter()
+            .filter(|symbol| symbol.chars().count() == 1)
+            .collect();
+        let mut sources = targets.clone();
+        for c in s.chars().map(String::from) {
+            if !sources.contains(&c) {
+                sources.push(c);
+            }
+        }
+        targets.sort();
+        sources.sort();
+
+        // state n means that n edits have been made so far
+        let mut edits = HfstBasicTransducer::new();
+        let mut states = vec![HfstBasicTransducer::INITIAL_STATE];
+        states.extend((0..max_edits).map(|_| edits.add_state()));
+        for (n, &state) in states.iter().enumerate() {
+            edits.set_final_weight(state, 0.0);
+            for source in &sources {
+                edits.add_transition(state, state, source, source, 0.0)?;
+            }
+            let Some(&next) = states.get(n + 1) else {
+                continue;
+            };
+            for source in &sources {
+                edits.add_transition(state, next, source, EPSILON, 1.0)?;
+                for target in targets.iter().filter(|&target| target != source) {
+                    edits.add_transition(state, next, source, target, 1.0)?;
+                }
+            }
+            for target in &targets {
+                edits.add_transition(state, next, EPSILON, target, 1.0)?;
+            }
+        }
+
+        let mut composed = query.to_transducer(implementation_type)?;
+        composed
+            .compose(&edits.to_transducer(implementation_type)?)?
+            .compose(self)?;
+        let mut results: Vec<_> = composed
+            .extract_paths(None, Some(0))?
+            .map(|path| {
+                let (_, flags) = split_flags(&path.output);
+                LookupResult {
+                    output: path.output,
+                    weight: path.weight,
+                    flags,
+                    symbols: None,
+                    alignment: None,
+                    trace: None,
+                }
+            })
+            .collect();
+        results = deduplicate(results);
+        results.sort_by(|a, b| a.weight.total_cmp(&b.weight));
+        Ok(results)
+    }
+
+    /// Look up the string `s` in the *apply down* direction. The same as
+    /// [`HfstTransducer::lookup`].
+    pub fn lookup_down(&self, s: &str) -> Result<HfstLookup<'_>, LookupError> {
+        self.lookup(s)
+    }
+
+    /// Look up the string `s` in the *apply up* direction: The string is
+    /// matched against the output side, and the results are read off the
+    /// input side. For an analyser, this *generates* word forms from the
+    /// analysis `s`.
+    ///
+    /// This is not supported by optimized-lookup transducers (`.hfstol`),
+    /// which can only be looked up in one direction, so for them, this fails
+    /// with [`LookupError::UnsupportedTransducerType`].
+    pub fn lookup_up(&self, s: &str) -> Result<HfstLookup<'_>, LookupError> {
+        let sp = lookup_input_to_c(s)?;
+        let mut err: c_int = 0;
+        let handle = unsafe { hfst_sys::hfst_lookup_up(self.inner, sp.as_ptr(), &raw mut err) };
+        self.check_lookup(handle, err)?;
+        Ok(HfstLookup {
+            handle,
+            options: LookupOptions::default(),
+            transducer: PhantomData,
+        })
+    }
+
+    /// Compose this transducer with `other`, so that the output side of
+    /// `self` is fed to the input side of `other`. The composition is done in
+    /// place (like in the C++ API): `self` *becomes* the composed transducer,
+    /// and is returned again to allow chaining operations. `other` is left
+    /// untouched.
+    ///
+    /// Like all binary operations, the alphabets of the two transducers are
+    /// harmonized first, unless turned off with
+    /// [`HfstTransducer::set_harmonize`].
+    pub fn compose(&mut self, other: &HfstTransducer) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_compose(self.inner_mut(), other.inner, self.harmonize, &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Disjunct (union) this transducer with `other`, so that it accepts
+    /// everything that either of them accepted. Done in place, just like
+    /// [`HfstTransducer::compose`].
+    pub fn disjunct(&mut self, other: &HfstTransducer) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_disjunct(self.inner_mut(), other.inner, self.harmonize, &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Intersect this transducer with `other`, so that it only accepts what
+    /// both of them accepted. Done in place, just like
+    /// [`HfstTransducer::compose`].
+    pub fn intersect(&mut self, other: &HfstTransducer) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_intersect(self.inner_mut(), other.inner, self.harmonize, &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Concatenate `other` to the end of this transducer, so that it accepts
+    /// what `self` accepted, immediately followed by what `other` accepted.
+    ///
+    /// Like the other binary operations, this is done in place: `self` is
+    /// modified and returned again to allow chaining, while `other` is only
+    /// borrowed, and is left untouched. The caller keeps ownership of both.
+    pub fn concatenate(
+        &mut self,
+        other: &HfstTransducer,
+    ) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_concatenate(self.inner_mut(), other.inner, self.harmonize, &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Subtract the language of `other` from this transducer, so that it
+    /// accepts what `self` accepted, except what `other` accepts. Done in
+    /// place, just like [`HfstTransducer::compose`].
+    pub fn subtract(&mut self, other: &HfstTransducer) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_subtract(self.inner_mut(), other.inner, self.harmonize, &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Minimize this transducer in place, and return it again to allow
+    /// chaining operations. Useful to shrink the (often huge) transducers that
+    /// result from composition, before converting them to lookup format.
+    pub fn minimize(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_minimize(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Determinize this transducer in place, and return it again to allow
+    /// chaining operations. Weights are treated as part of the arc labels, so
+    /// this never fails on account of the weights. See
+    /// [`HfstTransducer::determinize_weighted`] for proper weighted
+    /// determinization.
+    pub fn determinize(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_determinize(self.inner_mut(), false, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Determinize this weighted transducer in place, pushing the weights
+    /// along, so that the result has at most one path per input string.
+    ///
+    /// Not all weighted transducers can be determinized this way (e.g.
+    /// cyclic ones with ambiguous weights); those give back a
+    /// [`HfstTransducerError::NotDeterminizable`] instead.
+    pub fn determinize_weighted(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_determinize(self.inner_mut(), true, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Remove all epsilon transitions of this transducer in place, and return
+    /// it again to allow chaining operations.
+    ///
+    /// ```no_run
+    /// # fn f(transducer: &mut hfst::HfstTransducer) -> Result<(), hfst::HfstTransducerError> {
+    /// transducer.remove_epsilons()?.determinize()?.minimize()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_epsilons(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_remove_epsilons(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Invert this transducer in place, by swapping the input and output
+    /// symbols of every arc. This turns an analyser into a generator, and
+    /// vice versa.
+    pub fn invert(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_invert(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Reverse this transducer in place, so that it accepts the reversed
+    /// strings of what it accepted before. Note that lookups on the reversed
+    /// transducer must be done with reversed input as well.
+    pub fn reverse(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_reverse(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Project this transducer to its input side in place, turning it into
+    /// an acceptor of the strings it accepted as input.
+    pub fn project_input(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_input_project(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Project this transducer to its output side in place, turning it into
+    /// an acceptor of the strings it produced as output.
+    pub fn project_output(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_output_project(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Repeat this transducer zero or more times (Kleene star), in place.
+    pub fn repeat_star(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_repeat_star(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Repeat this transducer one or more times (Kleene plus), in place.
+    pub fn repeat_plus(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_repeat_plus(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Repeat this transducer exactly `n` times, in place.
+    pub fn repeat_n(&mut self, n: u32) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_repeat_n(self.inner_mut(), n, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Repeat this transducer from `n` to `k` times (both inclusive), in
+    /// place.
+    pub fn repeat_n_to_k(&mut self, n: u32, k: u32) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_repeat_n_to_k(self.inner_mut(), n, k, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Prune this weighted transducer in place, keeping only the `n` best
+    /// (lowest weight) paths.
+    pub fn n_best(&mut self, n: u32) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_n_best(self.inner_mut(), n, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Push the weights of this transducer in place, towards either the
+    /// initial state or the final states, as given by `direction`. The weight
+    /// of each path stays the same, but is distributed differently over
+    /// its arcs.
+    pub fn push_weights(
+        &mut self,
+        direction: PushDirection,
+    ) -> Result<&mut Self, HfstTransducerError> {
+        let push_type = match direction {
+            PushDirection::ToInitialState => hfst_sys::TO_INITIAL_STATE,
+            PushDirection::ToFinalState => hfst_sys::TO_FINAL_STATE,
+        };
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_push_weights(self.inner_mut(), push_type, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Remove all paths with a weight larger than `max_weight` from this
+    /// weighted transducer, in place.
+    pub fn prune_weights(&mut self, max_weight: f32) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_prune_weights(self.inner_mut(), max_weight, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Make the symbol pair `input:output` freely insertable anywhere in this
+    /// transducer, in place. E.g. to allow flag diacritics or punctuation
+    /// anywhere in a transducer that was built at runtime.
+    pub fn insert_freely(
+        &mut self,
+        input: &str,
+        output: &str,
+    ) -> Result<&mut Self, HfstTransducerError> {
+        let input = str_to_c(input)?;
+        let output = str_to_c(output)?;
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_insert_freely(
+                self.inner_mut(),
+                input.as_ptr(),
+                output.as_ptr(),
+                self.harmonize,
+                &raw mut err,
+            )
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Like [`HfstTransducer::insert_freely`], but makes the whole of `other`
+    /// freely insertable, instead of a single symbol pair.
+    pub fn insert_freely_transducer(
+        &mut self,
+        other: &HfstTransducer,
+    ) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_insert_freely_transducer(
+                self.inner_mut(),
+                other.inner,
+                self.harmonize,
+                &raw mut err,
+            )
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Compose this transducer (typically a lexicon) with the intersection of
+    /// all the `rules` (typically two-level rule transducers), in one go, in
+    /// place. This is much cheaper than intersecting the rules first, and
+    /// composing the lexicon with the result after.
+    pub fn compose_intersect(
+        &mut self,
+        rules: &[HfstTransducer],
+    ) -> Result<&mut Self, HfstTransducerError> {
+        let rules: Vec<*const c_void> = rules.iter().map(|rule| rule.inner as *const _).collect();
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_compose_intersect(
+                self.inner_mut(),
+                rules.as_ptr(),
+                rules.len(),
+                &raw mut err,
+            )
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Make this transducer optional, in place, by disjuncting it with the
+    /// empty string (epsilon).
+    pub fn optionalize(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_optionalize(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Extract the paths of this transducer, i.e. enumerate its language.
+    ///
+    /// At most `max_num` paths are extracted, and each cycle in the
+    /// transducer is followed at most `max_cycles` times. `None` means no
+    /// limit. Extracting from a cyclic transducer without limiting the number
+    /// of cycles gives [`HfstTransducerError::TransducerIsCyclic`].
+    pub fn extract_paths(
+        &self,
+        max_num: Option<usize>,
+        max_cycles: Option<usize>,
+    ) -> Result<HfstPathsIterator, HfstTransducerError> {
+        // -1 means no limit in the C++ API
+        let max_num = max_num.map_or(-1, |n| n.min(c_int::MAX as usize) as c_int);
+        let max_cycles = max_cycles.map_or(-1, |n| n.min(c_int::MAX as usize) as c_int);
+        let mut err: c_int = 0;
+        let inner =
+            unsafe { hfst_sys::hfst_extract_paths(self.inner, max_num, max_cycles, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        assert!(!inner.is_null());
+        Ok(HfstPathsIterator { inner })
+    }
+
+    /// Extract `count` randomly chosen paths of this transducer. If
+    /// `obey_flags` is true, paths that are not allowed by the flag
+    /// diacritics of the transducer are never chosen.
+    pub fn extract_random_paths(
+        &self,
+        count: usize,
+        obey_flags: bool,
+    ) -> Result<HfstPathsIterator, HfstTransducerError> {
+        let count = count.min(c_int::MAX as usize) as c_int;
+        let mut err: c_int = 0;
+        let inner = unsafe {
+            hfst_sys::hfst_extract_random_paths(self.inner, count, obey_flags, &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        assert!(!inner.is_null());
+        Ok(HfstPathsIterator { inner })
+    }
+
+    /// Returns true if this transducer is cyclic, i.e. accepts infinitely
+    /// many strings. Path extraction from a cyclic transducer must limit the
+    /// number of cycles, see [`HfstTransducer::extract_paths`].
+    pub fn is_cyclic(&self) -> bool {
+        unsafe { hfst_sys::hfst_is_cyclic(self.inner) }
+    }
+
+    /// The backend implementation type of this transducer, or [`None`] if
+    /// libhfst reports a type we do not know about.
+    pub fn implementation_type(&self) -> Option<ImplementationType> {
+        ImplementationType::from_raw(unsafe { hfst_sys::hfst_transducer_type(self.inner) })
+    }
+
+    /// Collect statistics about this transducer: the number of states, arcs
+    /// and final states, whether it is deterministic and minimal, and its
+    /// implementation type.
+    ///
+    /// Note that this walks the entire transducer, so it can take a while
+    /// for large transducers.
+    pub fn stats(&self) -> Result<TransducerStats, HfstTransducerError> {
+        let mut raw = hfst_sys::HfstTransducerStats {
+            n_states: 0,
+            n_arcs: 0,
+            n_final_states: 0,
+            is_deterministic: false,
+            is_minimal: false,
+            implementation_type: hfst_sys::ERROR_TYPE as c_int,
+        };
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_transducer_stats(self.inner, &raw mut raw, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(TransducerStats {
+            states: raw.n_states,
+            arcs: raw.n_arcs,
+            final_states: raw.n_final_states,
+            deterministic: raw.is_deterministic,
+            minimal: raw.is_minimal,
+            implementation_type: ImplementationType::from_raw(raw.implementation_type),
+        })
+    }
+
+    /// Set the weight of every final state of this transducer to `weight`,
+    /// in place. `set_final_weights(0.0)` neutralizes the final weights.
+    pub fn set_final_weights(&mut self, weight: f32) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_set_final_weights(self.inner_mut(), weight, false, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Add `delta` to the weight of every final state of this transducer,
+    /// in place.
+    pub fn add_to_final_weights(&mut self, delta: f32) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_set_final_weights(self.inner_mut(), delta, true, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Write this transducer in AT&T tabular text format to `writer`, the
+    /// same format as `hfst-fst2txt` writes. Epsilons are written as `@0@`.
+    pub fn write_att<W: Write>(&self, mut writer: W) -> Result<(), HfstTransducerError> {
+        let mut err: c_int = 0;
+        let text = unsafe { hfst_sys::hfst_transducer_to_att(self.inner, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        assert!(!text.is_null());
+        // copy it out, so that we can free the c string before writing
+        let bytes = unsafe { std::slice::from_raw_parts(text as *const u8, strlen(text)) }.to_vec();
+        unsafe { hfst_sys::hfst_free(text as *mut c_void) };
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Convert this transducer to the implementation type `implementation_type`,
+    /// in place. Converting to [`ImplementationType::HfstOl`] or
+    /// [`ImplementationType::HfstOlw`] gives a transducer that can be used
+    /// for fast lookups, without going through an `.hfstol` file.
+    pub fn convert(
+        &mut self,
+        implementation_type: ImplementationType,
+    ) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe {
+            hfst_sys::hfst_convert(self.inner_mut(), implementation_type.to_raw(), &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Turn automatic harmonization of alphabets in binary operations (such
+    /// as [`HfstTransducer::compose`]) with this transducer as `self` on or
+    /// off. It is on by default.
+    ///
+    /// Harmonization makes sure that the unknown and identity symbols of each
+    /// transducer are expanded to cover the symbols of the other. With large
+    /// alphabets, this can use a lot of memory, so if the alphabets are known
+    /// to be compatible already (e.g. because of an explicit call to
+    /// [`HfstTransducer::harmonize`]), it can be turned off.
+    pub fn set_harmonize(&mut self, harmonize: bool) -> &mut Self {
+        self.harmonize = harmonize;
+        self
+    }
+
+    /// Harmonize the alphabets of this transducer and `other`, in place. Both
+    /// transducers are modified.
+    pub fn harmonize(
+        &mut self,
+        other: &mut HfstTransducer,
+    ) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_harmonize(self.inner_mut(), other.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// The alphabet of this transducer: every symbol it knows about,
+    /// including multichar symbols (such as `+N`), flag diacritics (such as
+    /// `@D.NeedNoun.ON@`), and the special symbols for epsilon, unknown and
+    /// identity.
+    pub fn alphabet(&self) -> Vec<String> {
+        let mut n_symbols: usize = 0;
+        let symbols = unsafe { hfst_sys::hfst_alphabet(self.inner, &raw mut n_symbols) };
+        take_c_string_array(symbols, n_symbols)
+    }
+
+    /// Split `s` into the symbols of this transducer, the same way as the
+    /// input of a lookup is split: At each position, the longest symbol of
+    /// the alphabet that matches is taken. Characters that are not covered
+    /// by any symbol become symbols of their own, so that they can be found
+    /// by checking the symbols against [`HfstTransducer::alphabet`].
+    pub fn tokenize_input(&self, s: &str) -> Vec<String> {
+        self.input_alphabet()
+            .tokenize(s)
+            .map(String::from)
+            .collect()
+    }
+
+    /// The name of this transducer. Empty, if it has none.
+    pub fn name(&self) -> String {
+        let name = unsafe { hfst_sys::hfst_get_name(self.inner) };
+        take_c_string(name)
+    }
+
+    /// Set the name of this transducer. It is stored in the header when the
+    /// transducer is written to a stream. Fails if `name` contains a NUL
+    /// byte.
+    pub fn set_name(&mut self, name: &str) -> Result<(), HfstTransducerError> {
+        let name = str_to_c(name)?;
+        unsafe { hfst_sys::hfst_set_name(self.inner_mut(), name.as_ptr()) };
+        Ok(())
+    }
+
+    /// Get the value of the property `key` of this transducer, or [`None`] if
+    /// it does not have that property. Properties are arbitrary key/value
+    /// pairs, that are stored in the header of the transducer, such as
+    /// provenance information. Fails if `key` contains a NUL byte.
+    pub fn property(&self, key: &str) -> Result<Option<String>, HfstTransducerError> {
+        let key = str_to_c(key)?;
+        let value = unsafe { hfst_sys::hfst_get_property(self.inner, key.as_ptr()) };
+        Ok((!value.is_null()).then(|| take_c_string(value)))
+    }
+
+    /// Set the property `key` of this transducer to `value`, overwriting any
+    /// previous value. Fails if `key` or `value` contains a NUL byte.
+    pub fn set_property(&mut self, key: &str, value: &str) -> Result<(), HfstTransducerError> {
+        let key = str_to_c(key)?;
+        let value = str_to_c(value)?;
+        unsafe { hfst_sys::hfst_set_property(self.inner_mut(), key.as_ptr(), value.as_ptr()) };
+        Ok(())
+    }
+
+    /// All the properties of this transducer, as `(key, value)` pairs.
+    pub fn properties(&self) -> Vec<(String, String)> {
+        let mut n_keys: usize = 0;
+        let keys = unsafe { hfst_sys::hfst_property_keys(self.inner, &raw mut n_keys) };
+        take_c_string_array(keys, n_keys)
+            .into_iter()
+            .filter_map(|key| {
+                // the keys come from libhfst, so they have no NUL bytes
+                let value = self.property(&key).ok().flatten()?;
+                Some((key, value))
+            })
+            .collect()
+    }
+
+    /// Eliminate all flag diacritics of this transducer, in place. The
+    /// constraints the flags expressed are compiled into the structure of the
+    /// transducer instead, so the result never has `@X.FEATURE.VALUE@`
+    /// symbols in it. Note that the transducer can grow considerably.
+    pub fn eliminate_flags(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_eliminate_flags(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Like [`HfstTransducer::eliminate_flags`], but only eliminate the flag
+    /// diacritics of the feature `name` (e.g. `"NeedNoun"`), in place.
+    pub fn eliminate_flag(&mut self, name: &str) -> Result<&mut Self, HfstTransducerError> {
+        let name = str_to_c(name)?;
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_eliminate_flag(self.inner_mut(), name.as_ptr(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// The single lowest weight path of this transducer, or [`None`] if it
+    /// accepts nothing. If several paths share the lowest weight, one of them
+    /// is chosen arbitrarily. This transducer is left untouched.
+    pub fn shortest_path(&self) -> Result<Option<HfstPath>, HfstTransducerError> {
+        let mut err: c_int = 0;
+        let inner = unsafe { hfst_sys::hfst_shortest_path(self.inner, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        assert!(!inner.is_null());
+        Ok(HfstPathsIterator { inner }.next())
+    }
+
+    /// Remove the symbols that are not used on any arc from the alphabet of
+    /// this transducer, in place. Substitutions and compositions tend to
+    /// leave many such symbols behind.
+    pub fn prune_alphabet(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_prune_alphabet(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Remove the states of this transducer that are not on any path from
+    /// the initial state to a final state, in place. This operation is called
+    /// `prune()` in the C++ API.
+    pub fn trim(&mut self) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_prune(self.inner_mut(), &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Returns true if the language of this transducer is included in the
+    /// language of `other`, i.e. every path of `self` is also a path of
+    /// `other`. Neither transducer is modified.
+    pub fn is_subset(&self, other: &HfstTransducer) -> Result<bool, HfstTransducerError> {
+        let mut err: c_int = 0;
+        let is_subset = unsafe { hfst_sys::hfst_is_subset(self.inner, other.inner, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(is_subset)
+    }
+
+    /// Returns true if this transducer and `other` are equivalent, i.e. have
+    /// the same paths with the same weights. Neither transducer is modified.
+    pub fn compare(&self, other: &HfstTransducer) -> Result<bool, HfstTransducerError> {
+        let mut err: c_int = 0;
+        let equivalent = unsafe {
+            hfst_sys::hfst_compare(self.inner, other.inner, self.harmonize, &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        Ok(equivalent)
+    }
+
+    /// Multiply every weight of this transducer (on arcs and final states)
+    /// by `factor`, in place.
+    pub fn scale_weights(&mut self, factor: f32) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_transform_weights(self.inner_mut(), factor, 0.0, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Add `delta` to every weight of this transducer (on arcs and final
+    /// states), in place. Note that the weight of a path then grows with
+    /// `delta` once per arc, so longer paths are affected more. To change
+    /// the weight of every path by the same amount, use
+    /// [`HfstTransducer::add_to_final_weights`].
+    pub fn add_to_weights(&mut self, delta: f32) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_transform_weights(self.inner_mut(), 1.0, delta, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+}
+
+impl IntoIterator for HfstInputStream {
+    type Item = Result<HfstTransducer, HfstInputStreamError>;
+    type IntoIter = HfstInputStreamIterator;
+
+    /// Read the transducers from this HfstInputStream, like
+    /// [`HfstInputStream::read_transducers`], but with the errors that stop
+    /// the reading given as items, instead of just ending the iteration.
+    fn into_iter(self) -> Self::IntoIter {
+        HfstInputStreamIterator {
+            stream: self,
+            done: false,
+        }
+    }
+}
+
+/// Iterator over the transducers of an [`HfstInputStream`], returned from
+/// [`HfstInputStream::into_iter`]. Ends after the first error.
+pub struct HfstInputStreamIterator {
+    stream: HfstInputStream,
+    done: bool,
+}
+
+impl Iterator for HfstInputStreamIterator {
+    type Item = Result<HfstTransducer, HfstInputStreamError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let inner = self.stream.inner;
+        if unsafe { hfst_sys::hfst_input_stream_is_eof(inner) } {
+            self.done = true;
+            return None;
+        }
+        if unsafe { hfst_sys::hfst_input_stream_is_bad(inner) } {
+            self.done = true;
+            return Some(Err(HfstInputStreamError::Bad));
+        }
+        let tr = unsafe { hfst_sys::hfst_transducer_from_stream(inner) };
+        if tr.is_null() {
+            // the stream is not at the end, so the transducer was broken
+            self.done = true;
+            return Some(Err(HfstInputStreamError::Bad));
+        }
+        Some(Ok(HfstTransducer::from_inner(tr)))
+    }
+}
+
+impl Drop for HfstInputStream {
+    fn drop(&mut self) {
+        unsafe {
+            hfst_sys::hfst_input_stream_close(self.inner);
+            //hfst_sys::hfst_input_stream_free(self.inner);
+        }
+    }
+}
+
+/// Which variant of the input gave the results in
+/// [`HfstTransducer::lookup_cased`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseVariant {
+    /// The input as it was given
+    Original,
+    /// The input all lowercased
+    Lowercase,
+    /// The input with only the first letter lowercased
+    FirstLowercase,
+}
+
+/// One result of a lookup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookupResult {
+    /// The output string, e.g. the analysis. It includes the flag diacritics,
+    /// unless [`LookupOptions::strip_flags`] is set.
+    pub output: String,
+    /// The weight of the result.
+    pub weight: f32,
+    /// The flag diacritics (such as `@D.NeedNoun.ON@`) that were on the
+    /// path, in order.
+    pub flags: Vec<String>,
+    /// The output as a vector of symbols, if
+    /// [`LookupOptions::output_symbols`] was set. Without flag diacritics, if
+    /// [`LookupOptions::strip_flags`] was set.
+    pub symbols: Option<Vec<String>>,
+    /// The `(input symbol, output symbol)` pairs of the path, if
+    /// [`LookupOptions::alignment`] was set. Epsilons are empty strings.
+    pub alignment: Option<Vec<(String, String)>>,
+    /// The path through the transducer that gave this result, if
+    /// [`LookupOptions::trace`] was set.
+    pub trace: Option<LookupTrace>,
+}
+
+/// The path through the transducer that a lookup result was read off. See
+/// [`LookupOptions::trace`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookupTrace {
+    /// The arcs of the path, in order, from the initial state to a final
+    /// state. Epsilon transitions are included.
+    pub arcs: Vec<TraceArc>,
+}
+
+impl LookupTrace {
+    /// The states that the path goes through, starting with the initial
+    /// state.
+    pub fn states(&self) -> Vec<HfstState> {
+        let Some(first) = self.arcs.first() else {
+            return vec![HfstBasicTransducer::INITIAL_STATE];
+        };
+        std::iter::once(first.source)
+            .chain(self.arcs.iter().map(|arc| arc.target))
+            .collect()
+    }
+}
+
+/// One arc of a [`LookupTrace`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceArc {
+    /// The state the arc goes from.
+    pub source: HfstState,
+    /// The state the arc goes to.
+    pub target: HfstState,
+    /// The input symbol of the arc. Epsilons are empty strings.
+    pub input: String,
+    /// The output symbol of the arc. Epsilons are empty strings.
+    pub output: String,
+    /// The weight of the arc.
+    pub weight: f32,
+}
+
+/// Copy out an array of `len` arcs that libhfst handed over to us, and free
+/// both the symbols of them, and the array.
+fn take_trace(arcs: *mut hfst_sys::HfstTraceArc, len: usize) -> LookupTrace {
+    if arcs.is_null() {
+        return LookupTrace { arcs: vec![] };
+    }
+    let trace = unsafe { std::slice::from_raw_parts(arcs, len) }
+        .iter()
+        .map(|arc| TraceArc {
+            source: arc.source,
+            target: arc.target,
+            input: take_c_string(arc.input),
+            output: take_c_string(arc.output),
+            weight: arc.weight,
+        })
+        .collect();
+    unsafe { hfst_sys::hfst_free(arcs as *mut c_void) };
+    LookupTrace { arcs: trace }
+}
+
+impl From<LookupResult> for (String, f32) {
+    fn from(result: LookupResult) -> Self {
+        (result.output, result.weight)
+    }
+}
+
+/// Represents a handle to a lookup in progress. This structure is returned
+/// from [`HfstTransducer::lookup`]. This type implements [`IntoIterator`],
+/// to iterate over the results in the lookup.
+///
+/// The lookup points into the transducer, so it borrows it, and can not
+/// outlive it:
+/// ```compile_fail
+/// # use hfst::HfstTransducer;
+/// let lookup = {
+///     let transducer = HfstTransducer::from_regex("a").unwrap();
+///     transducer.lookup("a").unwrap()
+/// };
+/// ```
+pub struct HfstLookup<'t> {
+    handle: *mut c_void,
+    // The options that are applied on the Rust side, as the results are read
+    options: LookupOptions,
+    // The transducer, or its converted copy with the `convert-for-lookup`
+    // feature, which is kept in the transducer
+    transducer: PhantomData<&'t HfstTransducer>,
+}
+
+impl<'t> HfstLookup<'t> {
+    /// Returns true if the lookup was cut off by
+    /// [`LookupOptions::time_cutoff`] before all results were found.
+    pub fn timed_out(&self) -> bool {
+        unsafe { hfst_sys::hfst_lookup_timed_out(self.handle) }
+    }
+
+    /// Returns true if the lookup was skipped, because the input was found
+    /// to be infinitely ambiguous. See
+    /// [`LookupOptions::check_infinitely_ambiguous`].
+    pub fn infinitely_ambiguous(&self) -> bool {
+        unsafe { hfst_sys::hfst_lookup_was_infinitely_ambiguous(self.handle) }
+    }
+
+    /// Iterate over the results as raw bytes, without requiring them to be
+    /// valid UTF-8, such as when the transducer was compiled from text in
+    /// some legacy encoding. Only the options that libhfst handles itself
+    /// (such as [`LookupOptions::max_results`]) apply, the results are
+    /// given exactly as libhfst outputs them.
+    pub fn into_bytes(self) -> HfstLookupBytesIterator<'t> {
+        let inner = unsafe { hfst_sys::hfst_lookup_iterator(self.handle) };
+        HfstLookupBytesIterator {
+            inner,
+            _lookup: self,
+        }
+    }
+}
+
+impl Drop for HfstLookup<'_> {
+    fn drop(&mut self) {
+        unsafe { hfst_sys::hfst_lookup_free(self.handle) };
+    }
+}
+
+impl<'t> IntoIterator for HfstLookup<'t> {
+    type Item = Result<LookupResult, LookupError>;
+    type IntoIter = HfstLookupIterator<'t>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        let inner = unsafe { hfst_sys::hfst_lookup_iterator(self.handle) };
+
+        HfstLookupIterator {
+            inner,
+            options: std::mem::take(&mut self.options),
+            buffered: None,
+            _lookup: self,
+        }
+    }
+}
+
+pub struct HfstLookupIterator<'t> {
+    // Opaque pointer to a "struct ResultIterator"
+    inner: *mut hfst_sys::ResultIterator,
+    options: LookupOptions,
+    // When the options need all results up front (e.g. to sort them), they
+    // are read into this buffer on the first call to next()
+    buffered: Option<std::vec::IntoIter<Result<LookupResult, LookupError>>>,
+    // the underlying HfstLookup, which the iterator points into. It is freed
+    // after the iterator, when this struct is dropped
+    _lookup: HfstLookup<'t>,
+}
+
+impl Drop for HfstLookupIterator<'_> {
+    fn drop(&mut self) {
+        unsafe { hfst_sys::hfst_lookup_iterator_free(self.inner) };
+    }
+}
+
+/// Read the output and weight of the result the libhfst iterator `inner` is
+/// at, without advancing it. Returns [`None`] when there are no more results.
+fn lookup_iterator_value(inner: *mut hfst_sys::ResultIterator) -> Option<(Vec<u8>, f32)> {
+    if unsafe { hfst_sys::hfst_lookup_iterator_done(inner) } {
+        return None;
+    }
+    let mut s: *mut c_char = std::ptr::null_mut();
+    let mut w: c_float = 0.0;
+    unsafe {
+        hfst_sys::hfst_lookup_iterator_value(inner, addr_of_mut!(s), &raw mut w);
+    }
+    Some((take_c_bytes(s), w))
+}
+
+impl HfstLookupIterator<'_> {
+    /// Do the options require us to see all results before returning any?
+    fn needs_buffering(&self) -> bool {
+        self.options.sort_by_weight || self.options.deduplicate
+    }
+
+    /// Read the next result from libhfst that passes the filters of the
+    /// options, without any buffering.
+    fn next_unbuffered(&mut self) -> Option<Result<LookupResult, LookupError>> {
+        loop {
+            let mut result = match self.next_raw()? {
+                Ok(result) => result,
+                Err(e) => return Some(Err(e)),
+            };
+            // libhfst should already have discarded these, but not all
+            // backends support the cutoff
+            if self
+                .options
+                .max_weight
+                .is_some_and(|max| result.weight > max)
+            {
+                continue;
+            }
+            if let Some(ResultFilter(filter)) = &self.options.filter {
+                let filtered = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    filter(&result.output, result.weight)
+                }));
+                match filtered {
+                    Ok(Some(weight)) => result.weight = weight,
+                    Ok(None) => continue,
+                    Err(panic) => return Some(Err(LookupError::Panicked(panic_message(&*panic)))),
+                }
+            }
+            return Some(Ok(result));
+        }
+    }
+
+    /// Read the next result from libhfst, as-is.
+    fn next_raw(&mut self) -> Option<Result<LookupResult, LookupError>> {
+        let (bytes, w) = lookup_iterator_value(self.inner)?;
+        let symbols = self.options.output_symbols.then(|| {
+            let mut n_symbols: usize = 0;
+            let symbols =
+                unsafe { hfst_sys::hfst_lookup_iterator_symbols(self.inner, &raw mut n_symbols) };
+            let mut symbols = take_c_string_array(symbols, n_symbols);
+            if self.options.strip_flags {
+                symbols.retain(|symbol| !is_flag_diacritic(symbol));
+            }
+            symbols
+        });
+        let alignment = self.options.alignment.then(|| {
+            let mut n_pairs: usize = 0;
+            let pairs =
+                unsafe { hfst_sys::hfst_lookup_iterator_alignment(self.inner, &raw mut n_pairs) };
+            // the input and output symbols alternate in the array
+            let symbols = take_c_string_array(pairs, 2 * n_pairs);
+            let mut symbols = symbols.into_iter();
+            std::iter::from_fn(|| Some((symbols.next()?, symbols.next()?))).collect()
+        });
+        let trace = self.options.trace.then(|| {
+            let mut n_arcs: usize = 0;
+            let arcs = unsafe { hfst_sys::hfst_lookup_iterator_trace(self.inner, &raw mut n_arcs) };
+            take_trace(arcs, n_arcs)
+        });
+        unsafe { hfst_sys::hfst_lookup_iterator_next(self.inner) };
+
+        let rust_string = match String::from_utf8(bytes) {
+            Ok(rust_string) => rust_string,
+            Err(e) => return Some(Err(e.into())),
+        };
+        let (stripped, flags) = split_flags(&rust_string);
+        let output = if self.options.strip_flags {
+            stripped
+        } else {
+            rust_string
+        };
+        Some(Ok(LookupResult {
+            output,
+            weight: w,
+            flags,
+            symbols,
+            alignment,
+            trace,
+        }))
+    }
+}
+
+/// The message of a panic caught with [`std::panic::catch_unwind`].
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Remove the results with the same output as an earlier one, keeping the
+/// lowest weight one of them, in the position of the first one.
+fn deduplicate(results: Vec<LookupResult>) -> Vec<LookupResult> {
+    let mut deduplicated: Vec<LookupResult> = Vec::with_capacity(results.len());
+    let mut positions: std::collections::HashMap<String, usize> = Default::default();
+    for result in results {
+        match positions.get(&result.output) {
+            Some(&i) => {
+                if result.weight < deduplicated[i].weight {
+                    deduplicated[i] = result;
+                }
+            }
+            None => {
+                positions.insert(result.output.clone(), deduplicated.len());
+                deduplicated.push(result);
+            }
+        }
+    }
+    deduplicated
+}
+
+impl Iterator for HfstLookupIterator<'_> {
+    /// The type of the elements being iterated over. In the lookup case,
+    /// the full string, as well as a weight, and the flag diacritics, or an
+    /// error if the output is not valid UTF-8.
+    type Item = Result<LookupResult, LookupError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.needs_buffering() {
+            return self.next_unbuffered();
+        }
+        if self.buffered.is_none() {
+            let results: Result<Vec<_>, _> =
+                std::iter::from_fn(|| self.next_unbuffered()).collect();
+            let buffered = match results {
+                Ok(mut results) => {
+                    if self.options.deduplicate {
+                        results = deduplicate(results);
+                    }
+                    if self.options.sort_by_weight {
+                        // stable, so results of equal weight keep their order
+                        results.sort_by(|a, b| a.weight.total_cmp(&b.weight));
+                    }
+                    results.into_iter().map(Ok).collect()
+                }
+                // the results can not be sorted when one of them is missing
+                Err(e) => vec![Err(e)],
+            };
+            self.buffered = Some(buffered.into_iter());
+        }
+        self.buffered.as_mut().and_then(|buffered| buffered.next())
+    }
+}
+
+/// One result of a lookup, as raw bytes. See [`HfstLookup::into_bytes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookupResultBytes {
+    /// The output, exactly as libhfst gave it, including any flag
+    /// diacritics.
+    pub output: Vec<u8>,
+    /// The weight of the result.
+    pub weight: f32,
+}
+
+/// Iterator over the results of a lookup as raw bytes. Returned from
+/// [`HfstLookup::into_bytes`].
+pub struct HfstLookupBytesIterator<'t> {
+    // Opaque pointer to a "struct ResultIterator"
+    inner: *mut hfst_sys::ResultIterator,
+    // the underlying HfstLookup, see HfstLookupIterator
+    _lookup: HfstLookup<'t>,
+}
+
+impl Drop for HfstLookupBytesIterator<'_> {
+    fn drop(&mut self) {
+        unsafe { hfst_sys::hfst_lookup_iterator_free(self.inner) };
+    }
+}
+
+impl Iterator for HfstLookupBytesIterator<'_> {
+    type Item = LookupResultBytes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (output, weight) = lookup_iterator_value(self.inner)?;
+        unsafe { hfst_sys::hfst_lookup_iterator_next(self.inner) };
+        Some(LookupResultBytes { output, weight })
+    }
+}
+
+/// One path through a transducer, as returned by
+/// [`HfstTransducer::extract_paths`],
+/// [`HfstTransducer::extract_random_paths`] and
+/// [`HfstTransducer::shortest_path`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HfstPath {
+    /// The input side of the path.
+    pub input: String,
+    /// The output side of the path.
+    pub output: String,
+    /// The total weight of the path.
+    pub weight: f32,
+}
+
+/// Iterator over the paths extracted from a transducer. This structure is
+/// returned from [`HfstTransducer::extract_paths`] and
+/// [`HfstTransducer::extract_random_paths`]. The paths are copied out
+/// of the transducer up front, so this iterator does not borrow it.
+pub struct HfstPathsIterator {
+    // Opaque pointer to the C side paths iterator, which owns the paths
+    inner: *mut c_void,
+}
+
+impl Iterator for HfstPathsIterator {
+    type Item = HfstPath;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if unsafe { hfst_sys::hfst_paths_iterator_done(self.inner) } {
+            return None;
+        }
+        let mut input: *mut c_char = std::ptr::null_mut();
+        let mut output: *mut c_char = std::ptr::null_mut();
+        let mut weight: c_float = 0.0;
+        unsafe {
+            hfst_sys::hfst_paths_iterator_value(
+                self.inner,
+                &raw mut input,
+                &raw mut output,
+                &raw mut weight,
+            );
+        }
+        // the strings are owned by the iterator, and only valid until next()
+        let path = HfstPath {
+            input: c_charptr_to_owned_string(input),
+            output: c_charptr_to_owned_string(output),
+            weight,
+        };
+        unsafe { hfst_sys::hfst_paths_iterator_next(self.inner) };
+        Some(path)
+    }
+}
+
+impl Drop for HfstPathsIterator {
+    fn drop(&mut self) {
+        unsafe { hfst_sys::hfst_paths_iterator_free(self.inner) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    const PATH: &'static str = "/usr/share/giella/nob/analyser-gt-desc.hfstol";
+    use super::*;
+
+    /// Convert `transducer` to an optimized-lookup transducer, as only those
+    /// can be looked up in.
+    fn for_lookup(mut transducer: HfstTransducer) -> HfstTransducer {
+        transducer.convert(ImplementationType::HfstOlw).unwrap();
+        transducer
+    }
+
+    /// The paths of `transducer` as sorted `input:output` strings.
+    fn pairs(transducer: &HfstTransducer) -> Vec<String> {
+        let mut pairs: Vec<_> = transducer
+            .extract_paths(None, None)
+            .unwrap()
+            .map(|path| format!("{}:{}", path.input, path.output))
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    #[test]
+    fn can_open_inputstream() {
+        let input_stream = HfstInputStream::new(PATH);
+        assert!(input_stream.is_ok());
+    }
+
+    #[test]
+    fn errors_on_opening_nonexistant() {
+        let input_stream = HfstInputStream::new("/this/path/doesnt/exist");
+        assert!(matches!(
+            input_stream,
+            Err(HfstInputStreamError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn from_reader_errors_on_garbage() {
+        let input_stream = HfstInputStream::from_reader(&b"not a transducer"[..]);
+        assert!(matches!(
+            input_stream,
+            Err(HfstInputStreamError::NotTransducerStream)
+        ));
+    }
+
+    #[test]
+    fn output_stream_roundtrip() {
+        let path = std::env::temp_dir().join("hfst-rust-output-stream-roundtrip.hfst");
+        let transducer = HfstTransducer::from_regex("{cat}:{katt}").unwrap();
+        let mut output_stream =
+            HfstOutputStream::new(&path, ImplementationType::OpenFstTropical).unwrap();
+        output_stream.write(&transducer).unwrap();
+        output_stream.close().unwrap();
+
+        let input_stream = HfstInputStream::new(&path).unwrap();
+        let read_back = input_stream.read_only_transducer().unwrap();
+        assert!(read_back.compare(&transducer).unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn can_read_gzipped() {
+        let path = std::env::temp_dir().join("hfst-rust-can-read-gzipped.hfst");
+        let gz_path = std::env::temp_dir().join("hfst-rust-can-read-gzipped.hfst.gz");
+        let transducer = HfstTransducer::from_regex("{cat}:{katt}").unwrap();
+        HfstOutputStream::new(&path, ImplementationType::OpenFstTropical)
+            .unwrap()
+            .write(&transducer)
+            .unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&gz_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(&std::fs::read(&path).unwrap()).unwrap();
+        encoder.finish().unwrap();
+
+        let input_stream = HfstInputStream::new(&gz_path).unwrap();
+        let read_back = input_stream.read_only_transducer().unwrap();
+        assert!(read_back.compare(&transducer).unwrap());
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(gz_path).unwrap();
+    }
+
+    #[cfg(feature = "zhfst")]
+    #[test]
+    fn can_read_zhfst() {
+        use zip::write::SimpleFileOptions;
+
+        let transducer_bytes = |regex: &str| {
+            let path = std::env::temp_dir().join("hfst-rust-can-read-zhfst.hfst");
+            // spellers are optimized-lookup transducers
+            let transducer = for_lookup(HfstTransducer::from_regex(regex).unwrap());
+            HfstOutputStream::new(&path, ImplementationType::HfstOlw)
+                .unwrap()
+                .write(&transducer)
+                .unwrap();
+            let bytes = std::fs::read(&path).unwrap();
+            std::fs::remove_file(path).unwrap();
+            bytes
+        };
+        let index = r#"<?xml version="1.0" encoding="utf-8"?>
+            <hfstspeller dtdversion="1.0" hfstversion="3">
+              <info><locale>se</locale><title>Test speller</title></info>
+              <acceptor type="general" id="acceptor.default.hfst"/>
+              <errmodel id="errmodel.default.hfst"/>
+            </hfstspeller>"#;
+
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+        let options = SimpleFileOptions::default();
+        zip.start_file("index.xml", options).unwrap();
+        zip.write_all(index.as_bytes()).unwrap();
+        zip.start_file("acceptor.default.hfst", options).unwrap();
+        zip.write_all(&transducer_bytes("{áđđa}")).unwrap();
+        zip.start_file("errmodel.default.hfst", options).unwrap();
+        zip.write_all(&transducer_bytes("?* [d:đ] ?*")).unwrap();
+        let zip = zip.finish().unwrap();
+
+        let archive = zhfst::ZhfstArchive::from_reader(zip).unwrap();
+        assert_eq!(archive.locale.as_deref(), Some("se"));
+        assert_eq!(archive.title.as_deref(), Some("Test speller"));
+        assert!(
+            archive
+                .acceptor
+                .lookup("áđđa")
+                .unwrap()
+                .into_iter()
+                .next()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn properties_can_be_set_and_read() {
+        let mut tr = HfstTransducer::from_regex("a").unwrap();
+        tr.set_property("author", "giellatekno").unwrap();
+        assert_eq!(
+            tr.property("author").unwrap().as_deref(),
+            Some("giellatekno")
+        );
+        assert!(tr.property("missing").unwrap().is_none());
+        assert!(
+            tr.properties()
+                .contains(&("author".into(), "giellatekno".into()))
+        );
+    }
+
+    #[test]
+    fn name_and_properties_error_on_nul_byte() {
+        let mut tr = HfstTransducer::from_regex("a").unwrap();
+        assert!(matches!(
+            tr.set_name("ana\0lyser"),
+            Err(HfstTransducerError::ContainsNul(3))
+        ));
+        assert!(tr.set_property("a\0", "b").is_err());
+        assert!(tr.set_property("a", "b\0").is_err());
+        assert!(tr.property("a\0").is_err());
+    }
+
+    #[test]
+    fn read_named_transducers_gives_names() {
+        let path = std::env::temp_dir().join("hfst-rust-read-named-transducers.hfst");
+        let mut named = HfstTransducer::from_regex("a").unwrap();
+        named.set_name("analyser").unwrap();
+        let unnamed = HfstTransducer::from_regex("b").unwrap();
+        let mut output_stream =
+            HfstOutputStream::new(&path, ImplementationType::OpenFstTropical).unwrap();
+        output_stream
+            .write(&named)
+            .unwrap()
+            .write(&unnamed)
+            .unwrap();
+        output_stream.close().unwrap();
+
+        let input_stream = HfstInputStream::new(&path).unwrap();
+        let names: Vec<_> = input_stream
+            .read_named_transducers()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, [Some("analyser".to_string()), None]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn input_stream_knows_implementation_type() {
+        let input_stream = HfstInputStream::new(PATH).unwrap();
+        assert!(matches!(
+            input_stream.implementation_type(),
+            Some(ImplementationType::HfstOl | ImplementationType::HfstOlw)
+        ));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn can_lookup_mmapped() {
+        // SAFETY: The installed analyser is not modified during the tests
+        let input_stream = unsafe { HfstInputStream::from_mmap(PATH) }.unwrap();
+        let transducer = input_stream.read_only_transducer().unwrap();
+        assert!(
+            transducer
+                .lookup("sko")
+                .unwrap()
+                .into_iter()
+                .next()
+                .is_some()
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn can_load_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let transducer = runtime
+            .block_on(HfstTransducer::load_async(PATH))
+            .unwrap()
+            .unwrap();
+        assert!(
+            transducer
+                .lookup("sko")
+                .unwrap()
+                .into_iter()
+                .next()
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn new_with_progress_reports_progress() {
+        let mut last = (0, 0);
+        let input_stream =
+            HfstInputStream::new_with_progress(PATH, |read, total| last = (read, total)).unwrap();
+        assert!(input_stream.read_only_transducer().is_some());
+        assert!(last.1 > 0);
+        assert_eq!(last.0, last.1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_work() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = std::ffi::OsStr::from_bytes(b"hfst-rust-non-utf8-\xff.hfst");
+        let path = std::env::temp_dir().join(name);
+        let transducer = HfstTransducer::from_regex("a").unwrap();
+        HfstOutputStream::new(&path, ImplementationType::OpenFstTropical)
+            .unwrap()
+            .write(&transducer)
+            .unwrap();
+        let input_stream = HfstInputStream::new(&path).unwrap();
+        assert!(input_stream.read_only_transducer().is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "sha256")]
+    #[test]
+    fn new_verified_checks_hash() {
+        use sha2::{Digest, Sha256};
+
+        let sha256: String = Sha256::digest(std::fs::read(PATH).unwrap())
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect();
+        assert!(HfstInputStream::new_verified(PATH, &sha256).is_ok());
+        let wrong = "0".repeat(64);
+        assert!(matches!(
+            HfstInputStream::new_verified(PATH, &wrong),
+            Err(HfstInputStreamError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn clone_is_independent() {
+        let mut transducer = HfstTransducer::from_regex("a:b").unwrap();
+        let copy = transducer.clone();
+        transducer
+            .compose(&HfstTransducer::from_regex("b:c").unwrap())
+            .unwrap();
+        let output = |transducer: &HfstTransducer| {
+            for_lookup(transducer.clone())
+                .lookup("a")
+                .unwrap()
+                .into_iter()
+                .next()
+                .unwrap()
+                .unwrap()
+                .output
+        };
+        assert_eq!(output(&transducer), "c");
+        assert_eq!(output(&copy), "b");
+    }
+
+    #[test]
+    fn basic_transducer_can_be_looked_up_in() {
+        let mut basic = HfstBasicTransducer::new();
+        let s1 = basic.add_state();
+        basic
+            .add_transition(HfstBasicTransducer::INITIAL_STATE, s1, "a", "A", 0.5)
+            .unwrap();
+        basic.add_transition(s1, s1, "b", "B", 0.0).unwrap();
+        basic.set_final_weight(s1, 0.25);
+        let transducer = for_lookup(
+            basic
+                .to_transducer(ImplementationType::OpenFstTropical)
+                .unwrap(),
+        );
+
+        let results: Vec<_> = transducer
+            .lookup("abb")
+            .unwrap()
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output, "ABB");
+        assert_eq!(results[0].weight, 0.75);
+        assert_eq!(transducer.lookup("b").unwrap().into_iter().count(), 0);
+    }
+
+    #[test]
+    fn basic_transducer_add_transition_errors_on_nul_byte() {
+        let mut basic = HfstBasicTransducer::new();
+        let state = basic.add_state();
+        let result =
+            basic.add_transition(HfstBasicTransducer::INITIAL_STATE, state, "a\0", "b", 0.0);
+        assert!(matches!(result, Err(HfstTransducerError::ContainsNul(1))));
+    }
+
+    #[test]
+    fn from_pairs_errors_on_nul_byte() {
+        let result = HfstTransducer::from_pairs([("katt", "cat", 0.0), ("hund", "d\0g", 0.0)]);
+        assert!(matches!(result, Err(HfstTransducerError::ContainsNul(1))));
+    }
+
+    #[test]
+    fn lookup_results_can_be_dropped_midway() {
+        let transducer = for_lookup(
+            HfstTransducer::from_regex("a:{áđđa}::1 | a:{ŋuolla}::2 | a:{čáhci}::3").unwrap(),
+        );
+        let mut results = transducer.lookup("a").unwrap().into_iter();
+        let first = results.next().unwrap().unwrap();
+        assert!(["áđđa", "ŋuolla", "čáhci"].contains(&first.output.as_str()));
+        // the results that were not read are freed along with the iterator
+        drop(results);
+        assert_eq!(transducer.lookup("a").unwrap().into_iter().count(), 3);
+    }
+
+    #[test]
+    fn from_pair_has_one_path() {
+        let transducer = HfstTransducer::from_pair("kissa", "katt", 1.0).unwrap();
+        let paths: Vec<_> = transducer.extract_paths(None, None).unwrap().collect();
+        assert_eq!(
+            paths,
+            vec![HfstPath {
+                input: "kissa".to_string(),
+                output: "katt".to_string(),
+                weight: 1.0
+            }]
+        );
+    }
+
+    #[test]
+    fn from_pairs_is_disjunction() {
+        let pairs = [("kissa", "katt", 0.0), ("koira", "hund", 0.0)];
+        let transducer = HfstTransducer::from_pairs(pairs).unwrap();
+        let mut outputs: Vec<_> = transducer
+            .extract_paths(None, None)
+            .unwrap()
+            .map(|path| path.output)
+            .collect();
+        outputs.sort();
+        assert_eq!(outputs, vec!["hund", "katt"]);
+    }
+
+    #[test]
+    fn disjunct_accepts_either() {
+        let mut transducer = HfstTransducer::from_regex("a:b").unwrap();
+        let other = HfstTransducer::from_regex("c:d").unwrap();
+        transducer.disjunct(&other).unwrap();
+        assert_eq!(pairs(&transducer), ["a:b", "c:d"]);
+        assert_eq!(pairs(&other), ["c:d"]);
+    }
+
+    #[test]
+    fn intersect_accepts_only_both() {
+        let mut transducer = HfstTransducer::from_regex("a | b | c").unwrap();
+        transducer
+            .intersect(&HfstTransducer::from_regex("b | c | d").unwrap())
+            .unwrap();
+        assert_eq!(pairs(&transducer), ["b:b", "c:c"]);
+    }
+
+    #[test]
+    fn concatenate_appends_other() {
+        let mut transducer = HfstTransducer::from_regex("a:b").unwrap();
+        let other = HfstTransducer::from_regex("c | d:e").unwrap();
+        transducer.concatenate(&other).unwrap();
+        assert_eq!(pairs(&transducer), ["ac:bc", "ad:be"]);
+        assert_eq!(pairs(&other), ["c:c", "d:e"]);
+    }
+
+    #[test]
+    fn subtract_removes_other() {
+        let mut transducer = HfstTransducer::from_regex("a | b | c").unwrap();
+        transducer
+            .subtract(&HfstTransducer::from_regex("b | d").unwrap())
+            .unwrap();
+        assert_eq!(pairs(&transducer), ["a:a", "c:c"]);
+    }
+
+    #[test]
+    fn minimize_merges_states() {
+        let mut transducer =
+            HfstTransducer::from_pairs([("ab", "ab", 0.0), ("cb", "cb", 0.0)]).unwrap();
+        transducer.minimize().unwrap();
+        assert_eq!(pairs(&transducer), ["ab:ab", "cb:cb"]);
+        let stats = transducer.stats().unwrap();
+        assert_eq!(stats.states, 3);
+        assert!(stats.minimal);
+    }
+
+    #[test]
+    fn determinize_keeps_paths() {
+        let mut transducer =
+            HfstTransducer::from_pairs([("ab", "ab", 0.0), ("ac", "ac", 0.0)]).unwrap();
+        transducer.determinize().unwrap();
+        assert_eq!(pairs(&transducer), ["ab:ab", "ac:ac"]);
+        assert!(transducer.stats().unwrap().deterministic);
+    }
+
+    #[test]
+    fn determinize_weighted_keeps_lightest_path() {
+        let mut transducer = HfstTransducer::from_regex("a::1 | a::2").unwrap();
+        transducer.determinize_weighted().unwrap();
+        let paths: Vec<_> = transducer.extract_paths(None, None).unwrap().collect();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].input, "a");
+        assert_eq!(paths[0].weight, 1.0);
+    }
+
+    #[test]
+    fn remove_epsilons_removes_epsilon_arcs() {
+        let mut basic = HfstBasicTransducer::new();
+        let s1 = basic.add_state();
+        let s2 = basic.add_state();
+        let epsilon = "@_EPSILON_SYMBOL_@";
+        basic
+            .add_transition(
+                HfstBasicTransducer::INITIAL_STATE,
+                s1,
+                epsilon,
+                epsilon,
+                0.0,
+            )
+            .unwrap();
+        basic.add_transition(s1, s2, "a", "b", 0.0).unwrap();
+        basic.set_final_weight(s2, 0.0);
+        let mut transducer = basic
+            .to_transducer(ImplementationType::OpenFstTropical)
+            .unwrap();
+        assert_eq!(transducer.stats().unwrap().arcs, 2);
+        transducer.remove_epsilons().unwrap();
+        assert_eq!(transducer.stats().unwrap().arcs, 1);
+        assert_eq!(pairs(&transducer), ["a:b"]);
+    }
+
+    #[test]
+    fn invert_swaps_sides() {
+        let mut transducer = HfstTransducer::from_regex("{ab}:{cd}").unwrap();
+        transducer.invert().unwrap();
+        assert_eq!(pairs(&transducer), ["cd:ab"]);
+    }
+
+    #[test]
+    fn reverse_reverses_strings() {
+        let mut transducer = HfstTransducer::from_regex("{ab}:{cd}").unwrap();
+        transducer.reverse().unwrap();
+        assert_eq!(pairs(&transducer), ["ba:dc"]);
+    }
+
+    #[test]
+    fn projections_keep_one_side() {
+        let transducer = HfstTransducer::from_regex("{ab}:{cd}").unwrap();
+        let mut input = transducer.clone();
+        input.project_input().unwrap();
+        assert_eq!(pairs(&input), ["ab:ab"]);
+        let mut output = transducer.clone();
+        output.project_output().unwrap();
+        assert_eq!(pairs(&output), ["cd:cd"]);
+    }
+
+    #[test]
+    fn repeat_n_and_n_to_k() {
+        let transducer = HfstTransducer::from_regex("a:b").unwrap();
+        let mut twice = transducer.clone();
+        twice.repeat_n(2).unwrap();
+        assert_eq!(pairs(&twice), ["aa:bb"]);
+        let mut one_to_three = transducer.clone();
+        one_to_three.repeat_n_to_k(1, 3).unwrap();
+        assert_eq!(pairs(&one_to_three), ["a:b", "aa:bb", "aaa:bbb"]);
+    }
+
+    #[test]
+    fn repeat_star_and_plus() {
+        let empty = HfstTransducer::from_regex("0").unwrap();
+        let three = HfstTransducer::from_regex("a a a").unwrap();
+        let mut star = HfstTransducer::from_regex("a").unwrap();
+        star.repeat_star().unwrap();
+        assert!(star.is_cyclic());
+        assert!(empty.is_subset(&star).unwrap());
+        assert!(three.is_subset(&star).unwrap());
+        let mut plus = HfstTransducer::from_regex("a").unwrap();
+        plus.repeat_plus().unwrap();
+        assert!(plus.is_cyclic());
+        assert!(!empty.is_subset(&plus).unwrap());
+        assert!(three.is_subset(&plus).unwrap());
+    }
+
+    #[test]
+    fn n_best_keeps_lightest_paths() {
+        let pairs = [("a", "x", 3.0), ("a", "y", 1.0), ("a", "z", 2.0)];
+        let mut transducer = HfstTransducer::from_pairs(pairs).unwrap();
+        transducer.n_best(2).unwrap();
+        let mut paths: Vec<_> = transducer.extract_paths(None, None).unwrap().collect();
+        paths.sort_by(|a, b| a.weight.total_cmp(&b.weight));
+        let outputs: Vec<_> = paths.iter().map(|path| path.output.as_str()).collect();
+        assert_eq!(outputs, ["y", "z"]);
+    }
+
+    #[test]
+    fn push_weights_keeps_path_weights() {
+        let pairs = [("ab", "ab", 2.0), ("ac", "ac", 3.0)];
+        for direction in [PushDirection::ToInitialState, PushDirection::ToFinalState] {
+            let mut transducer = HfstTransducer::from_pairs(pairs).unwrap();
+            transducer.push_weights(direction).unwrap();
+            let mut paths: Vec<_> = transducer
+                .extract_paths(None, None)
+                .unwrap()
+                .map(|path| (path.input, path.weight))
+                .collect();
+            paths.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(paths, [("ab".to_string(), 2.0), ("ac".to_string(), 3.0)]);
+        }
+    }
+
+    #[test]
+    fn prune_weights_removes_heavy_paths() {
+        let pairs = [("a", "x", 1.0), ("a", "y", 2.0), ("a", "z", 3.0)];
+        let mut transducer = HfstTransducer::from_pairs(pairs).unwrap();
+        transducer.prune_weights(2.0).unwrap();
+        let mut outputs: Vec<_> = transducer
+            .extract_paths(None, None)
+            .unwrap()
+            .map(|path| path.output)
+            .collect();
+        outputs.sort();
+        assert_eq!(outputs, ["x", "y"]);
+    }
+
+    #[test]
+    fn insert_freely_allows_pair_anywhere() {
+        let mut transducer = HfstTransducer::from_regex("a b").unwrap();
+        transducer.insert_freely("x", "y").unwrap();
+        assert!(transducer.is_cyclic());
+        for regex in ["a b", "x:y a b", "a x:y b", "a b x:y x:y"] {
+            let expected = HfstTransducer::from_regex(regex).unwrap();
+            assert!(expected.is_subset(&transducer).unwrap(), "{regex}");
+        }
+        let unexpected = HfstTransducer::from_regex("a x b").unwrap();
+        assert!(!unexpected.is_subset(&transducer).unwrap());
+    }
+
+    #[test]
+    fn compose_intersect_applies_all_rules() {
+        let mut lexicon = HfstTransducer::from_regex("{ab} | {ac}").unwrap();
+        let rules = [
+            HfstTransducer::from_regex("[a | b | c:d]*").unwrap(),
+            HfstTransducer::from_regex("[a | b | c | c:d]*").unwrap(),
+        ];
+        lexicon.compose_intersect(&rules).unwrap();
+        assert_eq!(pairs(&lexicon), ["ab:ab", "ac:ad"]);
+    }
+
+    #[test]
+    fn optionalize_adds_empty_string() {
+        let mut transducer = HfstTransducer::from_regex("a:b").unwrap();
+        transducer.optionalize().unwrap();
+        assert_eq!(pairs(&transducer), [":", "a:b"]);
+    }
+
+    #[test]
+    fn extract_paths_obeys_limits() {
+        let pairs = [("a", "x", 0.0), ("b", "y", 0.0), ("c", "z", 0.0)];
+        let transducer = HfstTransducer::from_pairs(pairs).unwrap();
+        assert_eq!(transducer.extract_paths(Some(2), None).unwrap().count(), 2);
+
+        let cyclic = HfstTransducer::from_regex("a+").unwrap();
+        assert!(matches!(
+            cyclic.extract_paths(None, None),
+            Err(HfstTransducerError::TransducerIsCyclic)
+        ));
+        let inputs: Vec<_> = cyclic
+            .extract_paths(None, Some(1))
+            .unwrap()
+            .map(|path| path.input)
+            .collect();
+        assert!(inputs.contains(&"a".to_string()));
+        assert!(inputs.iter().all(|input| input.len() <= 2));
+    }
+
+    #[test]
+    fn extract_random_paths_gives_paths_of_transducer() {
+        let pairs = [("a", "x", 0.0), ("b", "y", 0.0)];
+        let transducer = HfstTransducer::from_pairs(pairs).unwrap();
+        let paths: Vec<_> = transducer.extract_random_paths(5, false).unwrap().collect();
+        assert!(!paths.is_empty());
+        assert!(paths.len() <= 5);
+        for path in paths {
+            assert!(pairs.contains(&(path.input.as_str(), path.output.as_str(), 0.0)));
+        }
+    }
+
+    #[test]
+    fn is_cyclic_detects_cycles() {
+        assert!(!HfstTransducer::from_regex("a b").unwrap().is_cyclic());
+        assert!(HfstTransducer::from_regex("a b+").unwrap().is_cyclic());
+    }
+
+    #[test]
+    fn stats_counts_states_and_arcs() {
+        let transducer = HfstTransducer::from_pair("ab", "cd", 0.0).unwrap();
+        let stats = transducer.stats().unwrap();
+        assert_eq!(stats.states, 3);
+        assert_eq!(stats.arcs, 2);
+        assert_eq!(stats.final_states, 1);
+        assert!(stats.deterministic);
+        assert_eq!(stats.implementation_type, transducer.implementation_type());
+    }
+
+    #[test]
+    fn final_weights_can_be_set_and_added_to() {
+        let mut transducer = HfstTransducer::from_regex("a:b").unwrap();
+        let weight = |transducer: &HfstTransducer| {
+            transducer
+                .extract_paths(None, None)
+                .unwrap()
+                .next()
+                .unwrap()
+                .weight
+        };
+        transducer.add_to_final_weights(2.0).unwrap();
+        assert_eq!(weight(&transducer), 2.0);
+        transducer.add_to_final_weights(0.5).unwrap();
+        assert_eq!(weight(&transducer), 2.5);
+        transducer.set_final_weights(1.0).unwrap();
+        assert_eq!(weight(&transducer), 1.0);
+    }
+
+    #[test]
+    fn eliminate_flags_removes_flags_and_blocked_paths() {
+        let regex = r#""@P.X.A@" a "@R.X.A@" | "@P.X.A@" b "@R.X.B@""#;
+        let mut transducer = HfstTransducer::from_regex(regex).unwrap();
+        transducer.eliminate_flags().unwrap();
+        assert_eq!(pairs(&transducer), ["a:a"]);
+
+        let mut transducer = HfstTransducer::from_regex(regex).unwrap();
+        transducer.eliminate_flag("X").unwrap();
+        assert_eq!(pairs(&transducer), ["a:a"]);
+        assert!(matches!(
+            transducer.eliminate_flag("X\0"),
+            Err(HfstTransducerError::ContainsNul(1))
+        ));
+    }
+
+    #[test]
+    fn prune_alphabet_removes_unused_symbols() {
+        let mut transducer = HfstTransducer::from_regex("a | %+N").unwrap();
+        transducer
+            .subtract(&HfstTransducer::from_regex("%+N").unwrap())
+            .unwrap();
+        transducer.prune_alphabet().unwrap();
+        let alphabet = transducer.alphabet();
+        assert!(alphabet.iter().any(|symbol| symbol == "a"));
+        assert!(!alphabet.iter().any(|symbol| symbol == "+N"));
+    }
+
+    #[test]
+    fn trim_removes_dead_states() {
+        let mut basic = HfstBasicTransducer::new();
+        let s1 = basic.add_state();
+        let dead = basic.add_state();
+        basic
+            .add_transition(HfstBasicTransducer::INITIAL_STATE, s1, "a", "b", 0.0)
+            .unwrap();
+        basic
+            .add_transition(HfstBasicTransducer::INITIAL_STATE, dead, "c", "d", 0.0)
+            .unwrap();
+        basic.set_final_weight(s1, 0.0);
+        let mut transducer = basic
+            .to_transducer(ImplementationType::OpenFstTropical)
+            .unwrap();
+        assert_eq!(transducer.stats().unwrap().states, 3);
+        transducer.trim().unwrap();
+        assert_eq!(transducer.stats().unwrap().states, 2);
+        assert_eq!(pairs(&transducer), ["a:b"]);
+    }
+
+    #[test]
+    fn weights_can_be_scaled_and_added_to() {
+        let weight = |transducer: &HfstTransducer| {
+            transducer
+                .extract_paths(None, None)
+                .unwrap()
+                .next()
+                .unwrap()
+                .weight
+        };
+        let mut transducer = HfstTransducer::from_pair("a", "b", 1.5).unwrap();
+        transducer.scale_weights(2.0).unwrap();
+        assert_eq!(weight(&transducer), 3.0);
+
+        // one arc and one final state
+        let mut transducer = HfstTransducer::from_regex("a:b").unwrap();
+        transducer.add_to_weights(1.0).unwrap();
+        assert_eq!(weight(&transducer), 2.0);
+    }
+
+    #[test]
+    fn from_att_reader_reads_att() {
+        let att = "0\t1\ta\tb\t0.5\n1\t0.0\n";
+        let transducer = HfstTransducer::from_att_reader(att.as_bytes()).unwrap();
+        let paths: Vec<_> = transducer.extract_paths(None, None).unwrap().collect();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].input, "a");
+        assert_eq!(paths[0].output, "b");
+    }
+
+    #[test]
+    fn att_roundtrip() {
+        let transducer = HfstTransducer::from_pair("ab", "ba", 0.0).unwrap();
+        let mut att = vec![];
+        transducer.write_att(&mut att).unwrap();
+        let transducer = HfstTransducer::from_att_reader(att.as_slice()).unwrap();
+        let paths: Vec<_> = transducer.extract_paths(None, None).unwrap().collect();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].input, "ab");
+        assert_eq!(paths[0].output, "ba");
+    }
+
+    #[test]
+    fn from_regex_compiles() {
+        let transducer = HfstTransducer::from_regex("a:b c").unwrap();
+        let paths: Vec<_> = transducer.extract_paths(None, None).unwrap().collect();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].input, "ac");
+        assert_eq!(paths[0].output, "bc");
+    }
+
+    #[test]
+    fn from_regex_errors_on_invalid_regex() {
+        let result = HfstTransducer::from_regex("a:b (c");
+        assert!(matches!(result, Err(HfstTransducerError::XreCompile(_))));
+    }
+
+    #[test]
+    fn from_regex_errors_on_nul_byte() {
+        let result = HfstTransducer::from_regex("a:b\0c");
+        assert!(matches!(result, Err(HfstTransducerError::ContainsNul(3))));
+    }
+
+    #[test]
+    fn alphabet_has_multichar_symbols() {
+        let transducer = HfstTransducer::from_regex("k a t t \"+N\":0").unwrap();
+        let alphabet = transducer.alphabet();
+        assert!(alphabet.iter().any(|symbol| symbol == "+N"));
+        assert!(alphabet.iter().any(|symbol| symbol == "k"));
+    }
+
+    #[test]
+    fn tokenize_input_takes_longest_match() {
+        let transducer = HfstTransducer::from_regex("{ka} %+N | k a %+Nom").unwrap();
+        assert_eq!(
+            transducer.tokenize_input("ka+Nom+Nx"),
+            ["k", "a", "+Nom", "+N", "x"]
+        );
+    }
+
+    #[test]
+    fn shortest_path_is_lightest() {
+        let pairs = [("a", "heavy", 2.0), ("a", "light", 1.0)];
+        let transducer = HfstTransducer::from_pairs(pairs).unwrap();
+        let path = transducer.shortest_path().unwrap().unwrap();
+        assert_eq!(path.output, "light");
+    }
+
+    #[test]
+    fn is_subset_of_disjunction() {
+        let small = HfstTransducer::from_pair("a", "b", 0.0).unwrap();
+        let big = HfstTransducer::from_pairs([("a", "b", 0.0), ("c", "d", 0.0)]).unwrap();
+        assert!(small.is_subset(&big).unwrap());
+        assert!(!big.is_subset(&small).unwrap());
+    }
+
+    #[test]
+    fn compare_regex_and_pair() {
+        let from_regex = HfstTransducer::from_regex("{ab}:{ba}").unwrap();
+        let from_pair = HfstTransducer::from_pair("ab", "ba", 0.0).unwrap();
+        let other = HfstTransducer::from_pair("ab", "ab", 0.0).unwrap();
+        assert!(from_regex.compare(&from_pair).unwrap());
+        assert!(!from_regex.compare(&other).unwrap());
+    }
+
+    #[test]
+    fn strip_flags_only_strips_flags() {
+        assert_eq!(strip_flags("@P.CASE.NOM@a@R.CASE@b@D.X.Y@"), "ab");
+        assert_eq!(strip_flags("a@b@c"), "a@b@c");
+        assert_eq!(strip_flags("@"), "@");
+        assert_eq!(strip_flags("x@@D.X@"), "x@");
+        assert_eq!(strip_flags("@X.A.B@"), "@X.A.B@");
+    }
+
+    #[test]
+    fn fold_diacritics_removes_diacritics() {
+        assert_eq!(fold_diacritics("áđđa"), "adda");
+        assert_eq!(fold_diacritics("Čáhcegáddi"), "Cahcegaddi");
+        assert_eq!(fold_diacritics("ŋuolggaŧ"), "nuolggat");
+        assert_eq!(fold_diacritics("+N+Sg"), "+N+Sg");
+    }
+
+    #[test]
+    fn fold_diacritics_keeps_marks_of_other_scripts() {
+        assert_eq!(fold_diacritics("мой"), "мой");
+        assert_eq!(fold_diacritics("άλφα"), "άλφα");
+        assert_eq!(fold_diacritics("Ёлка"), "Ёлка");
+        // a combining mark on a Latin letter is removed all the same
+        assert_eq!(fold_diacritics("n\u{303}o"), "no");
+    }
+
+    #[test]
+    fn lookup_ignoring_diacritics_finds_diacritics() {
+        let transducer = for_lookup(HfstTransducer::from_regex("{áđđa}:{adda+N}").unwrap());
+        let results = transducer.lookup_ignoring_diacritics("adda").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output, "adda+N");
+    }
+
+    #[test]
+    fn lookup_cased_tries_lowercased_variants() {
+        let transducer =
+            for_lookup(HfstTransducer::from_regex("{katt}:{cat} | {iPhone}:{phone}").unwrap());
+        let (variant, results) = transducer.lookup_cased("katt").unwrap().unwrap();
+        assert_eq!(variant, CaseVariant::Original);
+        assert_eq!(results[0].output, "cat");
+        for input in ["Katt", "KATT", "KAtt"] {
+            let (variant, results) = transducer.lookup_cased(input).unwrap().unwrap();
+            assert_eq!(variant, CaseVariant::Lowercase, "{input}");
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].output, "cat");
+        }
+        let (variant, results) = transducer.lookup_cased("IPhone").unwrap().unwrap();
+        assert_eq!(variant, CaseVariant::FirstLowercase);
+        assert_eq!(results[0].output, "phone");
+        assert!(transducer.lookup_cased("Hund").unwrap().is_none());
+    }
+
+    #[test]
+    fn lookup_prefix_counts_characters() {
+        let transducer =
+            for_lookup(HfstTransducer::from_regex("{áđđa}:{adda+N} | {áđ}:{ad+N}").unwrap());
+        let (n_chars, results) = transducer.lookup_prefix("áđđačáhci").unwrap().unwrap();
+        assert_eq!(n_chars, 4);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output, "adda+N");
+        let (n_chars, results) = transducer.lookup_prefix("áđa").unwrap().unwrap();
+        assert_eq!(n_chars, 2);
+        assert_eq!(results[0].output, "ad+N");
+        assert!(transducer.lookup_prefix("čáhci").unwrap().is_none());
+        assert!(transducer.lookup_prefix("").unwrap().is_none());
+    }
+
+    #[test]
+    fn lookup_with_word_boundary_finds_mwe() {
+        let transducer =
+            for_lookup(HfstTransducer::from_regex("[{giitu} %# {eatnat}] .x. {giitu}").unwrap());
+        let options = LookupOptions {
+            word_boundary: Some("#".to_string()),
+            ..Default::default()
+        };
+        let results: Vec<_> = transducer
+            .lookup_with_options("giitu eatnat", &options)
+            .unwrap()
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output, "giitu");
+    }
+
+    #[test]
+    fn lookup_with_filter_drops_and_rescores() {
+        let pairs = [("a", "keep", 1.0), ("a", "drop", 0.0)];
+        let transducer = for_lookup(HfstTransducer::from_pairs(pairs).unwrap());
+        let options = LookupOptions {
+            filter: Some(ResultFilter::new(|output, weight| {
+                (output == "keep").then_some(weight * 10.0)
+            })),
+            ..Default::default()
+        };
+        let results: Vec<_> = transducer
+            .lookup_with_options("a", &options)
+            .unwrap()
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output, "keep");
+        assert_eq!(results[0].weight, 10.0);
+    }
+
+    #[test]
+    fn lookup_with_panicking_filter_errors() {
+        let transducer = for_lookup(HfstTransducer::from_pairs([("a", "b", 0.0)]).unwrap());
+        let options = LookupOptions {
+            filter: Some(ResultFilter::new(|_, _| panic!("bad filter"))),
+            ..Default::default()
+        };
+        let mut results = transducer
+            .lookup_with_options("a", &options)
+            .unwrap()
+            .into_iter();
+        assert!(matches!(
+            results.next(),
+            Some(Err(LookupError::Panicked(message))) if message == "bad filter"
+        ));
+    }
+
+    #[test]
+    fn lookup_with_trace_gives_path() {
+        let transducer = for_lookup(HfstTransducer::from_regex("a:b c").unwrap());
+        let options = LookupOptions {
+            trace: true,
+            ..Default::default()
+        };
+        let result = transducer
+            .lookup_with_options("ac", &options)
+            .unwrap()
+            .into_iter()
+            .next()
+            .unwrap()
+            .unwrap();
+        let trace = result.trace.unwrap();
+        let pairs: Vec<_> = trace
+            .arcs
+            .iter()
+            .map(|arc| (arc.input.as_str(), arc.output.as_str()))
+            .collect();
+        assert_eq!(pairs, [("a", "b"), ("c", "c")]);
+        assert_eq!(trace.states().len(), 3);
+    }
+
+    #[test]
+    fn lookup_unknown_symbols() {
+        let transducer = for_lookup(HfstTransducer::from_regex("a ?").unwrap());
+        let lookup = |unknown_symbols| {
+            let options = LookupOptions {
+                unknown_symbols,
+                ..Default::default()
+            };
+            transducer
+                .lookup_with_options("ax", &options)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.unwrap().output)
+                .collect::<Vec<_>>()
+        };
+        assert!(lookup(UnknownSymbols::Fail).is_empty());
+        assert_eq!(lookup(UnknownSymbols::Identity), ["ax"]);
+    }
+
+    #[test]
+    fn lookup_errors_on_too_long_input() {
+        let transducer = for_lookup(HfstTransducer::from_regex("%+N a").unwrap());
+        let options = LookupOptions {
+            max_input_length: Some(2),
+            ..Default::default()
+        };
+        assert!(transducer.lookup_with_options("+Na", &options).is_ok());
+        assert!(matches!(
+            transducer.lookup_with_options("aaa", &options),
+            Err(LookupError::InputTooLong { length: 3, max: 2 })
+        ));
+    }
+
+    #[test]
+    fn lookup_input_length_is_checked_after_word_boundaries() {
+        let transducer = HfstTransducer::from_regex("a").unwrap();
+        let options = LookupOptions {
+            max_input_length: Some(3),
+            word_boundary: Some("..".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            transducer.lookup_with_options("a b", &options),
+            Err(LookupError::InputTooLong { length: 4, max: 3 })
+        ));
+    }
+
+    #[test]
+    fn lookup_of_very_long_input_is_rejected() {
+        let transducer = HfstTransducer::from_regex("%+N a").unwrap();
+        let options = LookupOptions {
+            max_input_length: Some(10),
+            ..Default::default()
+        };
+        let input = "+N".repeat(1 << 20);
+        assert!(matches!(
+            transducer.lookup_with_options(&input, &options),
+            Err(LookupError::InputTooLong {
+                length: 11,
+                max: 10
+            })
+        ));
+    }
+
+    #[test]
+    fn tokenize_input_follows_changes_to_the_alphabet() {
+        let mut transducer = HfstTransducer::from_regex("a").unwrap();
+        assert_eq!(transducer.tokenize_input("+N"), ["+", "N"]);
+        transducer
+            .disjunct(&HfstTransducer::from_regex("%+N").unwrap())
+            .unwrap();
+        assert_eq!(transducer.tokenize_input("+N"), ["+N"]);
+    }
+
+    #[test]
+    fn deduplicate_keeps_lowest_weight() {
+        let result = |output: &str, weight| LookupResult {
+            output: output.to_string(),
+            weight,
+            flags: vec![],
+            symbols: None,
+            alignment: None,
+            trace: None,
+        };
+        let results = vec![result("a", 2.0), result("b", 1.0), result("a", 0.5)];
+        assert_eq!(
+            deduplicate(results),
+            vec![result("a", 0.5), result("b", 1.0)]
+        );
+    }
+
+    #[test]
+    fn lookup_into_bytes_gives_raw_output() {
+        let transducer = for_lookup(HfstTransducer::from_regex("a:b c").unwrap());
+        let results: Vec<_> = transducer.lookup("ac").unwrap().into_bytes().collect();
+        assert_eq!(
+            results,
+            vec![LookupResultBytes {
+                output: b"bc".to_vec(),
+                weight: 0.0
+            }]
+        );
+    }
+
+    #[test]
+    fn lookup_fuzzy_finds_typos() {
+        let transducer = HfstTransducer::from_regex("{katt}:{cat}").unwrap();
+        let results = transducer.lookup_fuzzy("kat", 1).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output, "cat");
+        assert_eq!(results[0].weight, 1.0);
+        assert!(transducer.lookup_fuzzy("ka", 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn lookup_fuzzy_gives_each_output_once() {
+        let transducer = HfstTransducer::from_regex("{katt}:{cat}").unwrap();
+        let results = transducer.lookup_fuzzy("kat", 2).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output, "cat");
+        assert_eq!(results[0].weight, 1.0);
+    }
+
+    #[test]
+    fn infinite_ambiguity_check_of_input_with_nul_byte_fails() {
+        let tr = HfstTransducer::from_regex("a:b").unwrap();
+        assert!(!tr.is_lookup_infinitely_ambiguous("a").unwrap());
+        assert!(matches!(
+            tr.is_lookup_infinitely_ambiguous("a\0b"),
+            Err(LookupError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn lookup_of_input_with_nul_byte_fails() {
+        let transducer = HfstTransducer::from_regex("a").unwrap();
+        assert!(matches!(
+            transducer.lookup("a\0b"),
+            Err(LookupError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            transducer.lookup_with_options("a\0b", &LookupOptions::default()),
+            Err(LookupError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            transducer.lookup_symbols(&["a", "\0"]),
+            Err(LookupError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn lookup_up_in_optimized_lookup_transducer_fails() {
+        let transducer = HfstInputStream::new(PATH)
+            .unwrap()
+            .read_only_transducer()
+            .unwrap();
+        assert!(matches!(
+            transducer.lookup_up("sko+N+Msc+Sg+Indef"),
+            Err(LookupError::UnsupportedTransducerType(Some(
+                ImplementationType::HfstOl | ImplementationType::HfstOlw
+            )))
+        ));
+    }
+
+    #[test]
+    fn sync_transducer_can_lookup_from_multiple_threads() {
+        let transducer = HfstInputStream::new(PATH)
+            .unwrap()
+            .read_only_transducer()
+            .unwrap();
+        let transducer = SyncTransducer::new(transducer);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..100 {
+                        assert!(!transducer.lookup("sko").unwrap().is_empty());
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn can_lookup() {
+        let input_stream = HfstInputStream::new(PATH).unwrap();
+        let transducer = input_stream
+            .into_iter()
+            .next()
+            .expect("the hfst input stream has at least one transducer")
+            .unwrap();
+        let query = "sko";
+        let results = transducer.lookup(query).unwrap();
+        let mut seen = std::collections::HashMap::new();
+        seen.insert(
+            "sko+N+Msc+Pl+Indef@D.CmpOnly.FALSE@@D.CmpPref.TRUE@@D.NeedNoun.ON@",
+            false,
+        );
+        seen.insert(
+            "sko+N+Msc+Pl+Nynorsk+Indef@D.CmpOnly.FALSE@@D.CmpPref.TRUE@@D.NeedNoun.ON@",
+            false,
+        );
+        seen.insert(
+            "sko+N+Msc+Sg+Indef@D.CmpOnly.FALSE@@D.CmpPref.TRUE@@D.NeedNoun.ON@",
+            false,
+        );
+        seen.insert("sko+V+Imp", false);
+        seen.insert("sko+V+Inf", false);
+
+        for result in results {
+            let result = result.unwrap();
+            *seen.get_mut(result.output.as_str()).unwrap() = true;
+        }
+
+        assert!(seen.into_iter().all(|(_k, v)| v));
+    }
+
+    #[test]
+    fn lookup_with_max_results() {
+        let input_stream = HfstInputStream::new(PATH).unwrap();
+        let transducer = input_stream.read_only_transducer().unwrap();
+        let options = LookupOptions {
+            max_results: Some(2),
+            ..Default::default()
+        };
+        let results: Vec<_> = transducer
+            .lookup_with_options("sko", &options)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn lookup_with_max_weight_drops_heavy_results() {
+        let transducer = for_lookup(HfstTransducer::from_regex("a:b::1 | a:c::3").unwrap());
+        let options = LookupOptions {
+            max_weight: Some(2.0),
+            ..Default::default()
+        };
+        let results: Vec<_> = transducer
+            .lookup_with_options("a", &options)
+            .unwrap()
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].output, "b");
+    }
+
+    #[test]
+    fn lookup_sorted_by_weight() {
+        let input_stream = HfstInputStream::new(PATH).unwrap();
+        let transducer = input_stream.read_only_transducer().unwrap();
+        let options = LookupOptions {
+            sort_by_weight: true,
+            ..Default::default()
+        };
+        let weights: Vec<_> = transducer
+            .lookup_with_options("sko", &options)
+            .unwrap()
+            .into_iter()
+            .map(|result| result.unwrap().weight)
+            .collect();
+        assert!(weights.is_sorted_by(|a, b| a <= b));
     }
 
     // NOTE: This was a test meant to test that HfstTransducer::lookup worked correctly
@@ -319,8 +4122,8 @@ mod tests {
     //            let transducer = Arc::clone(&transducer);
     //            move || {
     //                let lookup = transducer.lookup("viessu");
-    //                for (s, _w) in lookup {
-    //                    println!("{s}");
+    //                for result in lookup {
+    //                    println!("{}", result.output);
     //                }
     //            }
     //        });
@@ -0,0 +1,85 @@
+//! Writing transducers to binary HFST files. Wraps the C++ `HfstOutputStream`.
+//!
+//! # Example
+//! ```no_run
+//! use hfst::{HfstOutputStream, HfstTransducer, ImplementationType};
+//!
+//! let transducer = HfstTransducer::from_regex("{cat}:{katt}").unwrap();
+//! let mut output_stream =
+//!     HfstOutputStream::new("cat.hfst", ImplementationType::OpenFstTropical).unwrap();
+//! output_stream.write(&transducer).unwrap();
+//! output_stream.close().unwrap();
+//! ```
+
+use std::ffi::c_int;
+use std::os::raw::c_void;
+use std::path::Path;
+
+use crate::{HfstTransducer, HfstTransducerError, ImplementationType, path_to_c_string};
+
+/// A stream for writing binary HFST transducers to a file, such as `.hfst`
+/// or `.hfstol` files. This structure is a wrapper around the C++
+/// HfstOutputStream.
+pub struct HfstOutputStream {
+    // An opaque pointer to an instance of the C++ HfstOutputStream class
+    inner: *mut c_void,
+    // Has `close()` been called? Otherwise, it is closed when dropped
+    closed: bool,
+}
+
+/// SAFETY: Same as for [`HfstTransducer`]: It can be moved between threads,
+/// but is *not* thread-safe.
+unsafe impl Send for HfstOutputStream {}
+
+impl HfstOutputStream {
+    /// Create the file at `path` (or truncate it, if it exists), for writing
+    /// transducers of the type `implementation_type` to it. To write an
+    /// optimized-lookup (`.hfstol`) file, use [`ImplementationType::HfstOl`].
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        implementation_type: ImplementationType,
+    ) -> Result<Self, HfstTransducerError> {
+        let path = path_to_c_string(path.as_ref())?;
+        let mut err: c_int = 0;
+        let inner = unsafe {
+            hfst_sys::hfst_output_stream(path.as_ptr(), implementation_type.to_raw(), &raw mut err)
+        };
+        HfstTransducerError::check(err)?;
+        assert!(!inner.is_null());
+        Ok(Self {
+            inner,
+            closed: false,
+        })
+    }
+
+    /// Write `transducer` to the stream, after the transducers that have
+    /// been written so far. The transducer must be of the implementation type
+    /// of the stream, see [`HfstTransducer::convert`].
+    pub fn write(&mut self, transducer: &HfstTransducer) -> Result<&mut Self, HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_output_stream_write(self.inner, transducer.inner, &raw mut err) };
+        HfstTransducerError::check(err)?;
+        Ok(self)
+    }
+
+    /// Flush and close the stream. Fails if the transducers could not be
+    /// written out in full, such as when the disk is full. Dropping the
+    /// stream also closes it, but then such errors are lost.
+    pub fn close(mut self) -> Result<(), HfstTransducerError> {
+        let mut err: c_int = 0;
+        unsafe { hfst_sys::hfst_output_stream_close(self.inner, &raw mut err) };
+        self.closed = true;
+        HfstTransducerError::check(err)
+    }
+}
+
+impl Drop for HfstOutputStream {
+    fn drop(&mut self) {
+        if !self.closed {
+            // nobody is there to hear about an error
+            let mut err: c_int = 0;
+            unsafe { hfst_sys::hfst_output_stream_close(self.inner, &raw mut err) };
+        }
+        unsafe { hfst_sys::hfst_output_stream_free(self.inner) };
+    }
+}
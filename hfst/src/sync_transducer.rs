@@ -0,0 +1,83 @@
+//! A transducer that can be shared between threads, such as in the state of
+//! an Axum or actix-web server.
+//!
+//! # Example
+//! ```no_run
+//! use std::sync::Arc;
+//! use hfst::{HfstInputStream, SyncTransducer};
+//!
+//! let transducer = HfstInputStream::new("analyser.hfstol")
+//!     .unwrap()
+//!     .read_only_transducer()
+//!     .unwrap();
+//! let transducer = Arc::new(SyncTransducer::new(transducer));
+//! std::thread::spawn({
+//!     let transducer = Arc::clone(&transducer);
+//!     move || transducer.lookup("viessu").unwrap()
+//! });
+//! ```
+
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{HfstTransducer, LookupError, LookupOptions, LookupResult};
+
+/// An [`HfstTransducer`] behind a [`Mutex`], so that it is [`Sync`], and
+/// can be looked up in from many threads. The lookups are done one at a
+/// time, so when throughput matters, have a look at the
+/// `transducer_actor` module (with the `tokio-actors` feature), or load
+/// one transducer per thread.
+pub struct SyncTransducer {
+    transducer: Mutex<HfstTransducer>,
+}
+
+impl SyncTransducer {
+    /// Wrap `transducer`, so it can be shared between threads.
+    pub fn new(transducer: HfstTransducer) -> Self {
+        Self {
+            transducer: Mutex::new(transducer),
+        }
+    }
+
+    /// Take the transducer back out.
+    pub fn into_inner(self) -> HfstTransducer {
+        self.transducer
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Lock the transducer. A panic in another thread while it held the
+    /// lock does not leave the transducer in a bad state, so the poisoning
+    /// is ignored.
+    fn lock(&self) -> MutexGuard<'_, HfstTransducer> {
+        self.transducer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Look up `s`, like [`HfstTransducer::lookup`]. The results are
+    /// collected while the transducer is locked, as the lookup can not
+    /// outlive the lock.
+    pub fn lookup(&self, s: &str) -> Result<Vec<LookupResult>, LookupError> {
+        self.lookup_with_options(s, &LookupOptions::default())
+    }
+
+    /// Look up `s` with the given `options`, like
+    /// [`HfstTransducer::lookup_with_options`].
+    pub fn lookup_with_options(
+        &self,
+        s: &str,
+        options: &LookupOptions,
+    ) -> Result<Vec<LookupResult>, LookupError> {
+        let transducer = self.lock();
+        transducer
+            .lookup_with_options(s, options)?
+            .into_iter()
+            .collect()
+    }
+}
+
+impl From<HfstTransducer> for SyncTransducer {
+    fn from(transducer: HfstTransducer) -> Self {
+        Self::new(transducer)
+    }
+}
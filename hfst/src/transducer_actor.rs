@@ -8,6 +8,15 @@
 //! infinite loop where it pulls off lookup requests, one by one. It does the
 //! lookup, and sends back the replies in a *oneshot* channel.
 //!
+//! Besides its main transducer, an actor can hold more transducers by name, such as
+//! the analyser and the generator of a language, see
+//! [`builder::Builder::named_transducer`] and [`HfstTransducerActor::lookup_in`].
+//!
+//! With the `tracing` feature, each lookup gets a `hfst_lookup` span inside the
+//! span of the caller, with the length of the input, how long it waited in the
+//! queue, and how long the lookup itself took (`input_len`, `queue_wait_us` and
+//! `ffi_us`).
+//!
 //! # Example
 //! ```rust
 //! use std::sync::Arc;
@@ -42,23 +51,177 @@
 //! }
 //! ```
 
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 
 use tokio::sync::{mpsc, oneshot};
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
 
-use crate::{HfstInputStream, HfstTransducer};
+use crate::{HfstInputStream, HfstTransducer, LookupOptions, LookupResult};
 
-/// A running HfstTransducer actor.
+/// A running HfstTransducer actor, or a pool of them.
 pub struct HfstTransducerActor {
+    // One per actor in the pool. The first one has the original transducer,
+    // the others have copies of it.
+    workers: Vec<Worker>,
+    // Where to start looking for the least busy worker, so that the lookups
+    // are spread evenly over idle workers
+    next_worker: AtomicUsize,
+    // Collect timings for the lookups?
+    timings: bool,
+    counters: Arc<Counters>,
+    // The names of the named transducers, which every worker has
+    transducer_names: HashSet<String>,
+}
+
+/// How many results of `lookup_stream()` can wait for the caller to read them, before
+/// the actor stops reading more.
+const STREAM_BUFFER: usize = 32;
+
+/// The number of buckets in the histogram of lookup durations.
+const HISTOGRAM_BUCKETS: usize = 32;
+
+/// The counters that the actors update, and `HfstTransducerActor::stats()` reads.
+#[derive(Default)]
+struct Counters {
+    cancelled: AtomicU64,
+    served: AtomicU64,
+    errors: AtomicU64,
+    // The number of lookups that were timed, and the sum of their durations, in
+    // nanoseconds
+    timed: AtomicU64,
+    lookup_nanos: AtomicU64,
+    // Histogram of the lookup durations: Bucket i counts the lookups that took less
+    // than 2^i microseconds (the last one also counts the longer ones)
+    histogram: [AtomicU64; HISTOGRAM_BUCKETS],
+}
+
+impl Counters {
+    /// Count a lookup that took `duration` (if it was timed).
+    fn record(&self, ok: bool, duration: Option<Duration>) {
+        self.served.fetch_add(1, Ordering::Relaxed);
+        if !ok {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(duration) = duration {
+            self.timed.fetch_add(1, Ordering::Relaxed);
+            let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+            self.lookup_nanos.fetch_add(nanos, Ordering::Relaxed);
+            // the number of bits needed for the microseconds
+            let bucket = (u128::BITS - duration.as_micros().leading_zeros()) as usize;
+            self.histogram[bucket.min(HISTOGRAM_BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The duration that the fraction `p` of the timed lookups took at most, rounded
+    /// up to the upper bound of its bucket.
+    fn percentile(&self, histogram: &[u64], p: f64) -> Option<Duration> {
+        let total: u64 = histogram.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let target = ((p * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        histogram.iter().enumerate().find_map(|(bucket, &count)| {
+            seen += count;
+            (seen >= target).then(|| Duration::from_micros(1 << bucket))
+        })
+    }
+
+    fn stats(&self, queue_depth: usize) -> ActorStats {
+        let histogram: Vec<u64> = self
+            .histogram
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        let timed = self.timed.load(Ordering::Relaxed);
+        let mean_lookup_duration = (timed > 0)
+            .then(|| Duration::from_nanos(self.lookup_nanos.load(Ordering::Relaxed) / timed));
+        ActorStats {
+            queue_depth,
+            served: self.served.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            cancelled: self.cancelled.load(Ordering::Relaxed),
+            mean_lookup_duration,
+            p50_lookup_duration: self.percentile(&histogram, 0.5),
+            p99_lookup_duration: self.percentile(&histogram, 0.99),
+        }
+    }
+}
+
+/// A snapshot of the statistics of an actor (or a pool of them), from
+/// [`HfstTransducerActor::stats`].
+///
+/// The lookup durations are only measured when the actor is built with
+/// `.timings(true)`, they are [`None`] otherwise. The percentiles are rounded up to
+/// the next power of two microseconds.
+#[derive(Debug, Clone, Default)]
+pub struct ActorStats {
+    /// How many messages are waiting in the queues right now.
+    pub queue_depth: usize,
+    /// How many lookups have been done. Each input of a
+    /// [`HfstTransducerActor::lookup_many`] counts as one lookup.
+    pub served: u64,
+    /// How many of the lookups that have been done failed.
+    pub errors: u64,
+    /// How many lookups were skipped, because the caller stopped waiting for them
+    /// before the actor got to them, such as when an HTTP client disconnects.
+    pub cancelled: u64,
+    /// The mean duration of the lookups.
+    pub mean_lookup_duration: Option<Duration>,
+    /// The duration that half of the lookups took at most.
+    pub p50_lookup_duration: Option<Duration>,
+    /// The duration that 99% of the lookups took at most.
+    pub p99_lookup_duration: Option<Duration>,
+}
+
+/// One actor of the pool.
+struct Worker {
     jh: tokio::task::JoinHandle<HfstTransducer>,
+    // The queue of the interactive lookups
     tx: mpsc::Sender<LookupMessage>,
+    // The queue of the background lookups, which are only done when there are no
+    // interactive lookups waiting
+    background_tx: mpsc::Sender<LookupMessage>,
+}
+
+impl Worker {
+    /// The queue for lookups of the given `priority`.
+    fn queue(&self, priority: Priority) -> &mpsc::Sender<LookupMessage> {
+        match priority {
+            Priority::Interactive => &self.tx,
+            Priority::Background => &self.background_tx,
+        }
+    }
+}
+
+/// The priority of a lookup. Each actor has one queue per priority, and only does
+/// the background lookups when there are no interactive ones waiting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// Lookups that someone is waiting for, such as the ones of an HTTP request. This
+    /// is the priority of [`HfstTransducerActor::lookup`] and the others that do not
+    /// take a priority.
+    #[default]
+    Interactive,
+    /// Lookups that can wait, such as the ones of analysing a corpus.
+    Background,
 }
 
 /// The result we get back from `HfstTransducerActor::lookup()`.
 pub struct LookupResults {
-    /// The actual results: The string, and the weight.
-    pub results: Vec<(String, f32)>,
+    /// The actual results: The string, the weight, and the flag diacritics.
+    pub results: Vec<LookupResult>,
+
+    /// Where the time went, if the actor was built with `.timings(true)`.
+    pub timings: Option<Timings>,
+}
 
+/// How long a lookup took, and where the time went.
+pub struct Timings {
     /// We did wait before we entered the queue, and if so, for how long?
     pub before_queue: Waited,
 
@@ -76,9 +239,14 @@ pub struct LookupResults {
 pub enum LookupError {
     #[error("channel to actor was closed")]
     ChannelClosed,
+    #[error("the actor has no transducer named {0:?}")]
+    UnknownTransducer(String),
+    #[error(transparent)]
+    Lookup(#[from] crate::LookupError),
 }
 
 /// Did we wait? If so, for how long?
+#[derive(Debug, Clone, Copy)]
 pub enum Waited {
     Yes(Duration),
     No,
@@ -86,28 +254,129 @@ pub enum Waited {
 
 /// Message that is sent to the lookup actor from the many clients.
 enum LookupMessage {
-    Lookup(String, oneshot::Sender<LookupReply>),
+    /// Look up the input in the named transducer, or in the main one if there is no name
+    Lookup(
+        Option<String>,
+        String,
+        LookupOptions,
+        oneshot::Sender<LookupReply>,
+        Trace,
+    ),
+
+    /// Look up all of the inputs, and reply with all the results at once
+    LookupMany(
+        Vec<String>,
+        LookupOptions,
+        oneshot::Sender<Vec<LookupReply>>,
+        Trace,
+    ),
+
+    /// Look up the input, and send each result in the stream as it is read. The
+    /// oneshot reply says if the lookup could be started.
+    LookupStream(
+        String,
+        LookupOptions,
+        oneshot::Sender<Result<(), crate::LookupError>>,
+        mpsc::Sender<(String, f32)>,
+        Trace,
+    ),
+
+    /// Swap in a new transducer, and reply with the old one
+    ReplaceTransducer(HfstTransducer, oneshot::Sender<HfstTransducer>),
+}
+
+/// The span of the caller of a lookup, and when the lookup was sent, so that the
+/// actor can make a span for the lookup inside the caller's span (with the `tracing`
+/// feature).
+#[cfg(feature = "tracing")]
+struct Trace {
+    parent: tracing::Span,
+    sent: Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl Trace {
+    fn current() -> Self {
+        Self {
+            parent: tracing::Span::current(),
+            sent: Instant::now(),
+        }
+    }
+
+    /// Enter the span of the lookup of `input`. The queue wait is the time from when
+    /// the message was sent until the lookup starts, so for `lookup_many()` it also
+    /// includes the earlier inputs of the message.
+    fn enter_lookup(&self, input: &str) -> LookupSpan {
+        let span = tracing::info_span!(
+            parent: &self.parent,
+            "hfst_lookup",
+            input_len = input.len(),
+            queue_wait_us = self.sent.elapsed().as_micros() as u64,
+            ffi_us = tracing::field::Empty,
+        );
+        LookupSpan(span.entered())
+    }
+}
+
+/// The span of a lookup in the actor, from [`Trace::enter_lookup`].
+#[cfg(feature = "tracing")]
+struct LookupSpan(tracing::span::EnteredSpan);
+
+#[cfg(feature = "tracing")]
+impl LookupSpan {
+    fn record_ffi_duration(&self, duration: Option<Duration>) {
+        if let Some(duration) = duration {
+            self.0.record("ffi_us", duration.as_micros() as u64);
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+struct Trace;
 
-    /// Message to quit the actor
-    Quit,
+#[cfg(not(feature = "tracing"))]
+impl Trace {
+    fn current() -> Self {
+        Trace
+    }
+
+    fn enter_lookup(&self, _input: &str) -> LookupSpan {
+        LookupSpan
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+struct LookupSpan;
+
+#[cfg(not(feature = "tracing"))]
+impl LookupSpan {
+    fn record_ffi_duration(&self, _duration: Option<Duration>) {}
 }
 
 /// Internal Reply message that is sent back from the actor to `HfstTransducerActor::lookup()`
 #[derive(Debug)]
 struct LookupReply {
-    results: Vec<(String, f32)>,
-    lookup_duration: Duration,
+    results: Result<Vec<LookupResult>, crate::LookupError>,
+    // None when timings are off
+    lookup_duration: Option<Duration>,
 }
 
 mod builder {
     use super::HfstTransducerActor;
     use crate::HfstTransducer;
+    use std::collections::HashMap;
     use std::num::NonZeroUsize;
 
     /// The builder for [`HfstTransducerActor`]. It takes three values:
     /// - **transducer** (*required*). An [`crate::HfstTransducer`]. The transducer to use.
     /// - **queue_size** (*required*) A [`std::num::NonZeroUsize`]. The size of the tokio mpsc queue.
     /// - **timings** (*optional*), a [`bool`]. Whether or not to return timings in lookups.
+    ///   Off by default.
+    /// - **pool_size** (*optional*), a [`std::num::NonZeroUsize`]. How many actors to spread
+    ///   the lookups over, see [`Builder::pool_size`]. 1 by default.
+    /// - **named_transducer** (*optional*, any number of them), a name and an
+    ///   [`crate::HfstTransducer`]. More transducers to look up in by name, see
+    ///   [`Builder::named_transducer`].
     ///
     /// ## Example
     /// ```
@@ -121,6 +390,8 @@ mod builder {
         transducer: A,
         queue_size: B,
         timings: C,
+        pool_size: NonZeroUsize,
+        named: HashMap<String, HfstTransducer>,
     }
 
     // Beware: Custom implemented type state pattern builder below...
@@ -140,10 +411,34 @@ mod builder {
                 transducer: TransducerEmpty,
                 queue_size: QueueSizeEmpty,
                 timings: TimingsEmpty,
+                pool_size: NonZeroUsize::MIN,
+                named: HashMap::new(),
             }
         }
     }
 
+    impl<A, B, C> Builder<A, B, C> {
+        /// Run a pool of `size` actors, each with its own copy of the
+        /// transducer, and spread the lookups over them. Each of the actors
+        /// has its own queue, of the queue size. The default is 1.
+        pub fn pool_size(self, size: NonZeroUsize) -> Self {
+            Self {
+                pool_size: size,
+                ..self
+            }
+        }
+
+        /// Add a transducer to look up in by `name`, with
+        /// [`HfstTransducerActor::lookup_in`], so that one actor can serve a whole
+        /// language pair, e.g. `"sme-analyser"`, `"sme-generator"` and
+        /// `"nob-analyser"`. A transducer that is added with a name that is already
+        /// taken replaces the earlier one.
+        pub fn named_transducer(mut self, name: impl Into<String>, tr: HfstTransducer) -> Self {
+            self.named.insert(name.into(), tr);
+            self
+        }
+    }
+
     #[doc(hidden)]
     impl Builder<TransducerEmpty, QueueSizeEmpty, TimingsEmpty> {
         pub fn transducer(
@@ -154,6 +449,8 @@ mod builder {
                 transducer: TransducerAdded(tr),
                 queue_size: QueueSizeEmpty,
                 timings: TimingsEmpty,
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
 
@@ -165,6 +462,8 @@ mod builder {
                 transducer: TransducerEmpty,
                 queue_size: QueueSizeAdded(size),
                 timings: TimingsEmpty,
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
 
@@ -176,6 +475,8 @@ mod builder {
                 transducer: TransducerEmpty,
                 queue_size: QueueSizeEmpty,
                 timings: TimingsAdded(enabled),
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
     }
@@ -191,6 +492,8 @@ mod builder {
                 transducer: self.transducer,
                 queue_size: QueueSizeAdded(size),
                 timings: TimingsEmpty,
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
 
@@ -202,6 +505,8 @@ mod builder {
                 transducer: self.transducer,
                 queue_size: QueueSizeEmpty,
                 timings: TimingsAdded(enabled),
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
     }
@@ -217,6 +522,8 @@ mod builder {
                 transducer: TransducerAdded(tr),
                 queue_size: self.queue_size,
                 timings: TimingsEmpty,
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
 
@@ -228,6 +535,8 @@ mod builder {
                 transducer: TransducerEmpty,
                 queue_size: self.queue_size,
                 timings: TimingsAdded(enabled),
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
     }
@@ -243,6 +552,8 @@ mod builder {
                 transducer: TransducerAdded(tr),
                 queue_size: QueueSizeEmpty,
                 timings: self.timings,
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
 
@@ -254,6 +565,8 @@ mod builder {
                 transducer: TransducerEmpty,
                 queue_size: QueueSizeAdded(size),
                 timings: self.timings,
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
     }
@@ -268,13 +581,21 @@ mod builder {
                 transducer: self.transducer,
                 queue_size: self.queue_size,
                 timings: TimingsAdded(enabled),
+                pool_size: self.pool_size,
+                named: self.named,
             }
         }
 
         pub fn build(self) -> HfstTransducerActor {
             let transducer = self.transducer.0;
             let queue_size = self.queue_size.0.get();
-            HfstTransducerActor::new(transducer, queue_size)
+            HfstTransducerActor::new(
+                transducer,
+                self.named,
+                queue_size,
+                false,
+                self.pool_size.get(),
+            )
         }
     }
 
@@ -283,7 +604,14 @@ mod builder {
         pub fn build(self) -> HfstTransducerActor {
             let transducer = self.transducer.0;
             let queue_size = self.queue_size.0.get();
-            HfstTransducerActor::new(transducer, queue_size)
+            let timings = self.timings.0;
+            HfstTransducerActor::new(
+                transducer,
+                self.named,
+                queue_size,
+                timings,
+                self.pool_size.get(),
+            )
         }
     }
 }
@@ -294,31 +622,229 @@ impl HfstTransducerActor {
         builder::Builder::default()
     }
 
-    fn new(transducer: HfstTransducer, queue_size: usize) -> HfstTransducerActor {
+    fn new(
+        transducer: HfstTransducer,
+        named: HashMap<String, HfstTransducer>,
+        queue_size: usize,
+        timings: bool,
+        pool_size: usize,
+    ) -> HfstTransducerActor {
+        let counters = Arc::new(Counters::default());
+        let transducer_names = named.keys().cloned().collect();
+        let copies: Vec<_> = (1..pool_size)
+            .map(|_| (transducer.clone(), named.clone()))
+            .collect();
+        let workers = std::iter::once((transducer, named))
+            .chain(copies)
+            .map(|(transducer, named)| {
+                Self::spawn_worker(
+                    transducer,
+                    named,
+                    queue_size,
+                    timings,
+                    Arc::clone(&counters),
+                )
+            })
+            .collect();
+        HfstTransducerActor {
+            workers,
+            next_worker: AtomicUsize::new(0),
+            timings,
+            counters,
+            transducer_names,
+        }
+    }
+
+    fn spawn_worker(
+        transducer: HfstTransducer,
+        named: HashMap<String, HfstTransducer>,
+        queue_size: usize,
+        timings: bool,
+        counters: Arc<Counters>,
+    ) -> Worker {
         let (tx, mut rx) = mpsc::channel(queue_size);
+        let (background_tx, mut background_rx) = mpsc::channel(queue_size);
 
         let jh = tokio::task::spawn(async move {
-            while let Some(msg) = rx.recv().await {
+            let mut transducer = transducer;
+            loop {
+                // The actor quits when both queues are closed (see `stop()`), and
+                // all the messages in them are done
+                let msg = tokio::select! {
+                    biased;
+                    Some(msg) = rx.recv() => msg,
+                    Some(msg) = background_rx.recv() => msg,
+                    else => break,
+                };
                 match msg {
-                    LookupMessage::Lookup(input, result_tx) => {
-                        let t0 = Instant::now();
-                        let results: Vec<_> = transducer.lookup(&input).into_iter().collect();
-                        let lookup_duration = t0.elapsed();
-                        let reply_message = LookupReply {
-                            results,
-                            lookup_duration,
+                    LookupMessage::Lookup(name, input, options, result_tx, trace) => {
+                        // The lookup future was dropped while the message was in the
+                        // queue, so nobody is waiting for the results
+                        if result_tx.is_closed() {
+                            counters.cancelled.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        let transducer = match &name {
+                            Some(name) => named
+                                .get(name)
+                                .expect("the name was checked by lookup_in()"),
+                            None => &transducer,
                         };
-                        result_tx
-                            .send(reply_message)
-                            .expect("reciever didn't hang up");
+                        let reply_message =
+                            Self::look_up(transducer, &input, &options, timings, &counters, &trace);
+                        // The client hung up if its lookup future was dropped
+                        // (such as when a request is cancelled). Nobody is
+                        // waiting for the reply then, so just carry on with
+                        // the other lookups.
+                        let _ = result_tx.send(reply_message);
+                    }
+                    LookupMessage::LookupMany(inputs, options, result_tx, trace) => {
+                        if result_tx.is_closed() {
+                            counters.cancelled.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        let reply_messages = inputs
+                            .iter()
+                            .map(|input| {
+                                Self::look_up(
+                                    &transducer,
+                                    input,
+                                    &options,
+                                    timings,
+                                    &counters,
+                                    &trace,
+                                )
+                            })
+                            .collect();
+                        let _ = result_tx.send(reply_messages);
+                    }
+                    LookupMessage::LookupStream(input, options, result_tx, stream_tx, trace) => {
+                        if result_tx.is_closed() {
+                            counters.cancelled.fetch_add(1, Ordering::Relaxed);
+                            continue;
+                        }
+                        // The actor waits for the caller to read the results, which
+                        // must not block the runtime, so the lookup is done on a
+                        // blocking thread, which the transducer is lent to
+                        let counters = Arc::clone(&counters);
+                        transducer = tokio::task::spawn_blocking(move || {
+                            Self::look_up_stream(
+                                &transducer,
+                                &input,
+                                &options,
+                                timings,
+                                &counters,
+                                &trace,
+                                result_tx,
+                                stream_tx,
+                            );
+                            transducer
+                        })
+                        .await
+                        .expect("streaming lookup did not panic");
+                    }
+                    LookupMessage::ReplaceTransducer(new_transducer, result_tx) => {
+                        let old_transducer = std::mem::replace(&mut transducer, new_transducer);
+                        // If the caller is gone, the old transducer is just dropped
+                        let _ = result_tx.send(old_transducer);
                     }
-                    LookupMessage::Quit => break,
                 }
             }
             transducer
         });
 
-        HfstTransducerActor { jh, tx }
+        Worker {
+            jh,
+            tx,
+            background_tx,
+        }
+    }
+
+    /// Do one lookup on the actor, and make the reply for it.
+    fn look_up(
+        transducer: &HfstTransducer,
+        input: &str,
+        options: &LookupOptions,
+        timings: bool,
+        counters: &Counters,
+        trace: &Trace,
+    ) -> LookupReply {
+        let span = trace.enter_lookup(input);
+        // the span needs the duration, even when timings are off
+        let t0 = (timings || cfg!(feature = "tracing")).then(Instant::now);
+        let results = transducer
+            .lookup_with_options(input, options)
+            .and_then(|lookup| lookup.into_iter().collect());
+        let elapsed = t0.map(|t0| t0.elapsed());
+        span.record_ffi_duration(elapsed);
+        let lookup_duration = elapsed.filter(|_| timings);
+        counters.record(results.is_ok(), lookup_duration);
+        LookupReply {
+            results,
+            lookup_duration,
+        }
+    }
+
+    /// Do one lookup on the actor, and send the results into `stream_tx` one by one, as
+    /// they are read. When the channel is full, this blocks until the caller has read
+    /// some of them, so the results are not read faster than the caller uses them.
+    /// The stream ends when all the results are sent, or when a result could not be
+    /// read. Must be run on a blocking thread.
+    #[allow(clippy::too_many_arguments)]
+    fn look_up_stream(
+        transducer: &HfstTransducer,
+        input: &str,
+        options: &LookupOptions,
+        timings: bool,
+        counters: &Counters,
+        trace: &Trace,
+        result_tx: oneshot::Sender<Result<(), crate::LookupError>>,
+        stream_tx: mpsc::Sender<(String, f32)>,
+    ) {
+        let span = trace.enter_lookup(input);
+        let t0 = (timings || cfg!(feature = "tracing")).then(Instant::now);
+        let ok = match transducer.lookup_with_options(input, options) {
+            Ok(lookup) => {
+                let _ = result_tx.send(Ok(()));
+                let mut ok = true;
+                for result in lookup {
+                    match result {
+                        Ok(LookupResult { output, weight, .. }) => {
+                            // The caller dropped the stream, so the rest of the
+                            // results are not needed
+                            if stream_tx.blocking_send((output, weight)).is_err() {
+                                break;
+                            }
+                        }
+                        Err(_) => {
+                            ok = false;
+                            break;
+                        }
+                    }
+                }
+                ok
+            }
+            Err(e) => {
+                let _ = result_tx.send(Err(e));
+                false
+            }
+        };
+        let elapsed = t0.map(|t0| t0.elapsed());
+        span.record_ffi_duration(elapsed);
+        counters.record(ok, elapsed.filter(|_| timings));
+    }
+
+    /// The worker with the most free space in its queue for `priority`, i.e. the
+    /// least busy one.
+    fn least_busy_worker(&self, priority: Priority) -> &Worker {
+        let n = self.workers.len();
+        let start = self.next_worker.fetch_add(1, Ordering::Relaxed) % n;
+        // max_by_key() gives the last of equals, so prefer the earlier ones
+        (0..n)
+            .map(|i| (i, &self.workers[(start + i) % n]))
+            .max_by_key(|(i, worker)| (worker.queue(priority).capacity(), std::cmp::Reverse(*i)))
+            .map(|(_, worker)| worker)
+            .expect("there is at least one worker")
     }
 
     /// Look up a value in the transducer.
@@ -344,68 +870,560 @@ impl HfstTransducerActor {
     /// }
     /// ```
     pub async fn lookup(&self, input: &str) -> Result<LookupResults, LookupError> {
-        if self.tx.is_closed() {
-            return Err(LookupError::ChannelClosed);
+        self.lookup_with_options(input, &LookupOptions::default())
+            .await
+    }
+
+    /// Look up a value in the transducer, with the given `options`. See
+    /// [`HfstTransducer::lookup_with_options`].
+    ///
+    /// A [`LookupOptions::filter`] is run on the actor, as the results are
+    /// read, so it should be quick, as it holds up the other lookups.
+    pub async fn lookup_with_options(
+        &self,
+        input: &str,
+        options: &LookupOptions,
+    ) -> Result<LookupResults, LookupError> {
+        self.lookup_with_priority(input, options, Priority::Interactive)
+            .await
+    }
+
+    /// Look up a value in the transducer, with the given `options`, and with the given
+    /// `priority`. See [`Priority`].
+    pub async fn lookup_with_priority(
+        &self,
+        input: &str,
+        options: &LookupOptions,
+        priority: Priority,
+    ) -> Result<LookupResults, LookupError> {
+        self.send_lookup(None, input, options, priority).await
+    }
+
+    /// Look up a value in the transducer that was added to the actor by `name`, with
+    /// [`builder::Builder::named_transducer`]. Fails with
+    /// [`LookupError::UnknownTransducer`] if there is no transducer by that name.
+    pub async fn lookup_in(&self, name: &str, input: &str) -> Result<LookupResults, LookupError> {
+        self.lookup_in_with_options(name, input, &LookupOptions::default())
+            .await
+    }
+
+    /// Like [`HfstTransducerActor::lookup_in`], but with the given `options`.
+    pub async fn lookup_in_with_options(
+        &self,
+        name: &str,
+        input: &str,
+        options: &LookupOptions,
+    ) -> Result<LookupResults, LookupError> {
+        self.lookup_in_with_priority(name, input, options, Priority::Interactive)
+            .await
+    }
+
+    /// Like [`HfstTransducerActor::lookup_in`], but with the given `options`, and with
+    /// the given `priority`. See [`Priority`].
+    pub async fn lookup_in_with_priority(
+        &self,
+        name: &str,
+        input: &str,
+        options: &LookupOptions,
+        priority: Priority,
+    ) -> Result<LookupResults, LookupError> {
+        if !self.transducer_names.contains(name) {
+            return Err(LookupError::UnknownTransducer(name.to_string()));
         }
+        self.send_lookup(Some(name.to_string()), input, options, priority)
+            .await
+    }
 
-        let tx = self.tx.clone();
+    /// Send the lookup of `input` in the transducer called `name` (or in the main one)
+    /// to the actor, and wait for the reply.
+    async fn send_lookup(
+        &self,
+        name: Option<String>,
+        input: &str,
+        options: &LookupOptions,
+        priority: Priority,
+    ) -> Result<LookupResults, LookupError> {
         let (os_tx, os_rx) = oneshot::channel();
-        let message = LookupMessage::Lookup(input.into(), os_tx);
-        let before_queue = match tx.try_send(message) {
-            Ok(()) => Waited::No,
-            Err(mpsc::error::TrySendError::Closed(_message)) => {
-                return Err(LookupError::ChannelClosed);
-            }
+        let message =
+            LookupMessage::Lookup(name, input.into(), options.clone(), os_tx, Trace::current());
+        let before_queue = self.send(message, priority).await?;
+
+        // Message has been sent here into the queue here. We don't know at what position
+        // in the queue it entered into, or if there even was a queue at all.
+        let t0 = self.timings.then(Instant::now);
+        // The reply is dropped without being sent if the actor is stopped
+        // before it gets to this lookup, see `stop_timeout()`
+        let lookup_reply = os_rx.await.map_err(|_| LookupError::ChannelClosed)?;
+        let result_duration = t0.map(|t0| t0.elapsed());
+
+        let busy = lookup_reply.lookup_duration.unwrap_or_default();
+        Self::lookup_results(lookup_reply, before_queue, result_duration, busy)
+    }
+
+    /// Look up a value in the transducer, and get the results as a stream of outputs
+    /// and weights, which the actor fills as it reads the results. This way, the
+    /// first few results of a very ambiguous input can be used without waiting for
+    /// all of them, and dropping the stream stops the actor from reading the rest.
+    ///
+    /// Only a few results are buffered: The actor stops reading results while the
+    /// stream is not read, so that they do not pile up in memory. It does no other
+    /// lookups meanwhile, so read the stream promptly, or drop it.
+    ///
+    /// This fails if the lookup could not be started. If a result can not be read
+    /// later on, such as when it is not valid UTF-8, the stream just ends there (and
+    /// it is counted in [`ActorStats::errors`]).
+    pub async fn lookup_stream(
+        &self,
+        input: &str,
+    ) -> Result<impl Stream<Item = (String, f32)> + Unpin + use<>, LookupError> {
+        self.lookup_stream_with_options(input, &LookupOptions::default())
+            .await
+    }
+
+    /// Like [`HfstTransducerActor::lookup_stream`], but with the given `options`.
+    pub async fn lookup_stream_with_options(
+        &self,
+        input: &str,
+        options: &LookupOptions,
+    ) -> Result<impl Stream<Item = (String, f32)> + Unpin + use<>, LookupError> {
+        let (os_tx, os_rx) = oneshot::channel();
+        let (stream_tx, stream_rx) = mpsc::channel(STREAM_BUFFER);
+        let message = LookupMessage::LookupStream(
+            input.into(),
+            options.clone(),
+            os_tx,
+            stream_tx,
+            Trace::current(),
+        );
+        self.send(message, Priority::Interactive).await?;
+        os_rx.await.map_err(|_| LookupError::ChannelClosed)??;
+        Ok(ReceiverStream::new(stream_rx))
+    }
+
+    /// Look up each of the `inputs` in the transducer. They are sent to the actor as
+    /// one message, so they are looked up one after the other, with no other lookups
+    /// in between, and the results come back in the same order as the inputs. Fails if
+    /// any of the lookups fails.
+    pub async fn lookup_many(&self, inputs: &[&str]) -> Result<Vec<LookupResults>, LookupError> {
+        self.lookup_many_with_options(inputs, &LookupOptions::default())
+            .await
+    }
+
+    /// Like [`HfstTransducerActor::lookup_many`], but with the given `options`.
+    pub async fn lookup_many_with_options(
+        &self,
+        inputs: &[&str],
+        options: &LookupOptions,
+    ) -> Result<Vec<LookupResults>, LookupError> {
+        self.lookup_many_with_priority(inputs, options, Priority::Interactive)
+            .await
+    }
+
+    /// Like [`HfstTransducerActor::lookup_many`], but with the given `options`, and
+    /// with the given `priority`. See [`Priority`].
+    pub async fn lookup_many_with_priority(
+        &self,
+        inputs: &[&str],
+        options: &LookupOptions,
+        priority: Priority,
+    ) -> Result<Vec<LookupResults>, LookupError> {
+        let (os_tx, os_rx) = oneshot::channel();
+        let inputs = inputs.iter().map(|input| input.to_string()).collect();
+        let message = LookupMessage::LookupMany(inputs, options.clone(), os_tx, Trace::current());
+        let before_queue = self.send(message, priority).await?;
+
+        let t0 = self.timings.then(Instant::now);
+        let lookup_replies = os_rx.await.map_err(|_| LookupError::ChannelClosed)?;
+        let result_duration = t0.map(|t0| t0.elapsed());
+
+        // the time the actor spent on the whole message
+        let busy = lookup_replies
+            .iter()
+            .filter_map(|reply| reply.lookup_duration)
+            .sum();
+        lookup_replies
+            .into_iter()
+            .map(|reply| Self::lookup_results(reply, before_queue, result_duration, busy))
+            .collect()
+    }
+
+    /// Send `message` to the queue for `priority` of the least busy actor, waiting for
+    /// room in the queue if it is full. Returns whether we had to wait.
+    async fn send(
+        &self,
+        message: LookupMessage,
+        priority: Priority,
+    ) -> Result<Waited, LookupError> {
+        let worker = self.least_busy_worker(priority);
+        let tx = worker.queue(priority);
+        if tx.is_closed() {
+            return Err(LookupError::ChannelClosed);
+        }
+
+        match tx.try_send(message) {
+            Ok(()) => Ok(Waited::No),
+            Err(mpsc::error::TrySendError::Closed(_message)) => Err(LookupError::ChannelClosed),
             Err(mpsc::error::TrySendError::Full(message)) => {
-                let t0 = Instant::now();
+                let t0 = self.timings.then(Instant::now);
                 match tx.reserve().await {
                     Ok(permit) => {
-                        let before_queue = Waited::Yes(t0.elapsed());
+                        let before_queue = match t0 {
+                            Some(t0) => Waited::Yes(t0.elapsed()),
+                            // not reported when timings are off
+                            None => Waited::No,
+                        };
                         permit.send(message);
-                        before_queue
-                    }
-                    Err(_) => {
-                        return Err(LookupError::ChannelClosed);
+                        Ok(before_queue)
                     }
+                    Err(_) => Err(LookupError::ChannelClosed),
                 }
             }
-        };
+        }
+    }
 
-        // Message has been sent here into the queue here. We don't know at what position
-        // in the queue it entered into, or if there even was a queue at all.
-        let t0 = Instant::now();
-        let lookup_reply = os_rx.await.expect("channel was not closed in transit");
-        let result_duration = t0.elapsed();
+    /// Swap in `transducer` for the main transducer of the running actor, such as to
+    /// load a new version of a model, and return the old transducer. The queue is kept, so no lookups are
+    /// lost: The ones that were in the (interactive) queue before this are done with
+    /// the old transducer, and the ones sent after it with the new one. Background
+    /// lookups that are already queued may be done with either.
+    ///
+    /// In a pool, each actor gets a copy of `transducer`, and swaps it in when it
+    /// gets to it, so for a short while some of them may still use the old one. The
+    /// old transducer of the first actor is returned.
+    pub async fn replace_transducer(
+        &self,
+        transducer: HfstTransducer,
+    ) -> Result<HfstTransducer, LookupError> {
+        let copies: Vec<_> = (1..self.workers.len())
+            .map(|_| transducer.clone())
+            .collect();
+        let mut replies = Vec::with_capacity(self.workers.len());
+        for (worker, transducer) in self
+            .workers
+            .iter()
+            .zip(std::iter::once(transducer).chain(copies))
+        {
+            let (os_tx, os_rx) = oneshot::channel();
+            let message = LookupMessage::ReplaceTransducer(transducer, os_tx);
+            worker
+                .tx
+                .send(message)
+                .await
+                .map_err(|_| LookupError::ChannelClosed)?;
+            replies.push(os_rx);
+        }
+        let mut old_transducers = vec![];
+        for os_rx in replies {
+            old_transducers.push(os_rx.await.map_err(|_| LookupError::ChannelClosed)?);
+        }
+        Ok(old_transducers.swap_remove(0))
+    }
 
+    /// Make the [`LookupResults`] of one lookup from the reply of the actor. The
+    /// actor spent `busy` on the message the lookup was in, and the reply came back
+    /// `result_duration` after it was sent (with timings on).
+    fn lookup_results(
+        lookup_reply: LookupReply,
+        before_queue: Waited,
+        result_duration: Option<Duration>,
+        busy: Duration,
+    ) -> Result<LookupResults, LookupError> {
         let LookupReply {
             results,
             lookup_duration,
         } = lookup_reply;
 
-        // Here we have to calculate a bit.
-        // We have result duration, which is the entire time from when the message
-        // was accepted into the queue, and we also have the actual time it took to look
-        // up the value, from the actor, so, we can calculate how long we waited in
-        // the queue.
-        let in_queue = Waited::Yes(result_duration - lookup_duration);
+        let timings =
+            result_duration
+                .zip(lookup_duration)
+                .map(|(result_duration, lookup_duration)| {
+                    // Here we have to calculate a bit.
+                    // We have result duration, which is the entire time from when the message
+                    // was accepted into the queue, and we also have the actual time it took to
+                    // look up the message, from the actor, so, we can calculate how long we
+                    // waited in the queue.
+                    let in_queue = Waited::Yes(result_duration.saturating_sub(busy));
+                    Timings {
+                        before_queue,
+                        in_queue,
+                        lookup_duration,
+                        result_duration,
+                    }
+                });
 
         Ok(LookupResults {
-            results,
-            before_queue,
-            in_queue,
-            result_duration,
-            lookup_duration,
+            results: results?,
+            timings,
         })
     }
 
-    /// Stop the actor. Returns the ownership of the underlying [`HfstTransducer`] back
-    /// the caller.
+    /// A snapshot of the statistics of the actor, or of all the actors of a pool
+    /// together.
+    ///
+    /// The statistics are kept with atomics, so this does not send anything to the
+    /// actors, and does not wait for them.
+    pub fn stats(&self) -> ActorStats {
+        let queue_depth = self
+            .workers
+            .iter()
+            .flat_map(|worker| [&worker.tx, &worker.background_tx])
+            .map(|tx| tx.max_capacity() - tx.capacity())
+            .sum();
+        self.counters.stats(queue_depth)
+    }
+
+    /// Stop the actor. The lookups that are already in the queue are done first, then
+    /// the actor quits, and the ownership of the underlying [`HfstTransducer`] is
+    /// returned back to the caller. For a pool, this is the original transducer, and
+    /// the copies are dropped. The named transducers are dropped too.
     pub async fn stop(self) -> HfstTransducer {
-        let HfstTransducerActor { tx, jh } = self;
-        let transducer = jh.await.expect("actor did not panic");
-        tx.send(LookupMessage::Quit)
-            .await
-            .expect("channel was not already closed");
+        // Closing the queues (by dropping the senders) lets the actors do the lookups
+        // that are already in them, and then quit
+        let join_handles: Vec<_> = self.workers.into_iter().map(|worker| worker.jh).collect();
+        let mut transducers = vec![];
+        for jh in join_handles {
+            transducers.push(jh.await.expect("actor did not panic"));
+        }
+        transducers.swap_remove(0)
+    }
+
+    /// Like [`HfstTransducerActor::stop`], but only wait for the queued lookups for
+    /// `timeout`. If they are not done by then, the actor is aborted: The lookups that
+    /// are still in the queue fail with [`LookupError::ChannelClosed`], and the
+    /// transducer is dropped along with the actor, so [`None`] is returned.
+    ///
+    /// This uses a tokio timer, so the time driver of the runtime must be enabled.
+    pub async fn stop_timeout(self, timeout: Duration) -> Option<HfstTransducer> {
+        let abort_handles: Vec<_> = self
+            .workers
+            .iter()
+            .map(|worker| worker.jh.abort_handle())
+            .collect();
+        match tokio::time::timeout(timeout, self.stop()).await {
+            Ok(transducer) => Some(transducer),
+            Err(_elapsed) => {
+                // A lookup that is running is not interrupted, the actor stops
+                // before the next one
+                for abort_handle in abort_handles {
+                    abort_handle.abort();
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ImplementationType, ResultFilter};
+    use std::num::NonZeroUsize;
+    use std::sync::Mutex;
+    use tokio_stream::StreamExt;
+
+    /// Compile `regex` into an optimized-lookup transducer, as only those can
+    /// be looked up in.
+    fn transducer(regex: &str) -> HfstTransducer {
+        let mut transducer = HfstTransducer::from_regex(regex).unwrap();
+        transducer.convert(ImplementationType::HfstOlw).unwrap();
         transducer
     }
+
+    fn actor(regex: &str) -> HfstTransducerActor {
+        HfstTransducerActor::builder()
+            .transducer(transducer(regex))
+            .queue_size(NonZeroUsize::new(10).unwrap())
+            .build()
+    }
+
+    fn outputs(results: LookupResults) -> Vec<String> {
+        results.results.into_iter().map(|r| r.output).collect()
+    }
+
+    #[tokio::test]
+    async fn stops_and_returns_transducer() {
+        let first = actor("a:b");
+        assert_eq!(outputs(first.lookup("a").await.unwrap()), ["b"]);
+        let transducer = first.stop().await;
+        assert!(transducer.lookup("a").unwrap().into_iter().next().is_some());
+
+        let second = actor("a:b");
+        assert!(second.stop_timeout(Duration::from_secs(10)).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn lookup_many_keeps_order() {
+        let actor = actor("a:x | b:y");
+        let outputs: Vec<_> = actor
+            .lookup_many(&["b", "c", "a"])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(outputs)
+            .collect();
+        assert_eq!(outputs, [vec!["y"], vec![], vec!["x"]]);
+    }
+
+    #[tokio::test]
+    async fn does_interactive_lookups_first() {
+        let actor = actor("a:x | b:y");
+        // the filter runs on the actor, so it sees the order of the lookups
+        let order = Arc::new(Mutex::new(vec![]));
+        let options = LookupOptions {
+            filter: Some(ResultFilter::new({
+                let order = Arc::clone(&order);
+                move |output, weight| {
+                    order.lock().unwrap().push(output.to_string());
+                    Some(weight)
+                }
+            })),
+            ..Default::default()
+        };
+        // both are queued before the actor gets to run
+        let (background, interactive) = tokio::join!(
+            actor.lookup_with_priority("a", &options, Priority::Background),
+            actor.lookup_with_priority("b", &options, Priority::Interactive),
+        );
+        assert!(background.is_ok() && interactive.is_ok());
+        assert_eq!(*order.lock().unwrap(), ["y", "x"]);
+    }
+
+    #[tokio::test]
+    async fn skips_cancelled_lookups() {
+        let actor = actor("a:b");
+        // the lookup is queued, and then dropped before the actor gets to it
+        assert!(
+            tokio::time::timeout(Duration::ZERO, actor.lookup("a"))
+                .await
+                .is_err()
+        );
+        assert!(actor.lookup("a").await.is_ok());
+        assert_eq!(actor.stats().cancelled, 1);
+    }
+
+    #[tokio::test]
+    async fn stats_count_lookups() {
+        let actor = HfstTransducerActor::builder()
+            .transducer(transducer("a:b"))
+            .queue_size(NonZeroUsize::new(10).unwrap())
+            .timings(true)
+            .build();
+        assert!(actor.stats().p50_lookup_duration.is_none());
+        actor.lookup_many(&["a", "b", "c"]).await.unwrap();
+        assert!(actor.lookup("a\0").await.is_err());
+        let stats = actor.stats();
+        assert_eq!(stats.queue_depth, 0);
+        assert_eq!(stats.served, 4);
+        assert_eq!(stats.errors, 1);
+        assert!(stats.mean_lookup_duration.is_some());
+        assert!(stats.p50_lookup_duration <= stats.p99_lookup_duration);
+    }
+
+    #[tokio::test]
+    async fn replaces_transducer() {
+        let actor = HfstTransducerActor::builder()
+            .transducer(transducer("a:b"))
+            .queue_size(NonZeroUsize::new(10).unwrap())
+            .pool_size(NonZeroUsize::new(2).unwrap())
+            .build();
+        assert_eq!(outputs(actor.lookup("a").await.unwrap()), ["b"]);
+        let new_transducer = transducer("a:c");
+        let old_transducer = actor.replace_transducer(new_transducer).await.unwrap();
+        assert_eq!(old_transducer.lookup("a").unwrap().into_iter().count(), 1);
+        for _ in 0..4 {
+            assert_eq!(outputs(actor.lookup("a").await.unwrap()), ["c"]);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn pool_spreads_lookups_over_workers() {
+        let actor = HfstTransducerActor::builder()
+            .transducer(transducer("a:x | b:y"))
+            .queue_size(NonZeroUsize::new(10).unwrap())
+            .pool_size(NonZeroUsize::new(2).unwrap())
+            .build();
+        // the filter holds each lookup until the other one is running too (or
+        // gives up after a while), and records how many ran at the same time
+        let running = Arc::new(AtomicUsize::new(0));
+        let most_running = Arc::new(AtomicUsize::new(0));
+        let options = LookupOptions {
+            filter: Some(ResultFilter::new({
+                let running = Arc::clone(&running);
+                let most_running = Arc::clone(&most_running);
+                move |_output, weight| {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most_running.fetch_max(now, Ordering::SeqCst);
+                    let deadline = Instant::now() + Duration::from_secs(5);
+                    while running.load(Ordering::SeqCst) < 2 && Instant::now() < deadline {
+                        std::thread::yield_now();
+                    }
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Some(weight)
+                }
+            })),
+            ..Default::default()
+        };
+        let (a, b) = tokio::join!(
+            actor.lookup_with_options("a", &options),
+            actor.lookup_with_options("b", &options),
+        );
+        assert_eq!(outputs(a.unwrap()), ["x"]);
+        assert_eq!(outputs(b.unwrap()), ["y"]);
+        assert_eq!(most_running.load(Ordering::SeqCst), 2);
+        assert_eq!(actor.stats().served, 2);
+    }
+
+    #[tokio::test]
+    async fn looks_up_in_named_transducers() {
+        let actor = HfstTransducerActor::builder()
+            .transducer(transducer("a:b"))
+            .named_transducer("generator", transducer("b:a"))
+            .queue_size(NonZeroUsize::new(10).unwrap())
+            .build();
+        assert_eq!(
+            outputs(actor.lookup_in("generator", "b").await.unwrap()),
+            ["a"]
+        );
+        assert!(actor.lookup("b").await.unwrap().results.is_empty());
+        assert!(matches!(
+            actor.lookup_in("analyser", "b").await,
+            Err(LookupError::UnknownTransducer(name)) if name == "analyser"
+        ));
+    }
+
+    #[tokio::test]
+    async fn streams_results() {
+        let actor = actor("a:b::1 | a:c::2");
+        let mut outputs: Vec<_> = actor
+            .lookup_stream("a")
+            .await
+            .unwrap()
+            .map(|(output, _weight)| output)
+            .collect()
+            .await;
+        outputs.sort();
+        assert_eq!(outputs, ["b", "c"]);
+        assert!(actor.lookup_stream("a\0").await.is_err());
+        // the stream is dropped before it is read
+        drop(actor.lookup_stream("a").await.unwrap());
+        assert_eq!(actor.stats().served, 3);
+    }
+
+    #[tokio::test]
+    async fn streams_more_results_than_it_buffers() {
+        let outputs: Vec<_> = (0..200).map(|i| format!("x{i}")).collect();
+        let pairs = outputs.iter().map(|output| ("a", output.as_str(), 0.0));
+        let mut transducer = HfstTransducer::from_pairs(pairs).unwrap();
+        transducer.convert(ImplementationType::HfstOlw).unwrap();
+        let actor = HfstTransducerActor::builder()
+            .transducer(transducer)
+            .queue_size(NonZeroUsize::new(10).unwrap())
+            .build();
+        let mut stream = actor.lookup_stream("a").await.unwrap();
+        assert!(stream.next().await.is_some());
+        // the actor is held up until the stream is read on
+        let rest: Vec<_> = stream.collect().await;
+        assert_eq!(rest.len(), 199);
+        assert!(actor.lookup("a").await.is_ok());
+    }
 }
@@ -0,0 +1,116 @@
+//! Reading the transducers out of `.zhfst` speller archives, such as the ones
+//! of the Divvun spellers.
+//!
+//! A `.zhfst` archive is a zip file with an `index.xml` that describes the
+//! speller, an *acceptor*, which accepts the correctly spelled words, and an
+//! *error model*, which maps misspellings to their corrections.
+//!
+//! # Example
+//! ```no_run
+//! use hfst::zhfst::ZhfstArchive;
+//!
+//! let archive = ZhfstArchive::open("se.zhfst").expect("a valid speller archive");
+//! println!("locale: {:?}", archive.locale);
+//! let acceptor = archive.acceptor;
+//! let error_model = archive.error_model;
+//! ```
+
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::{HfstInputStream, HfstInputStreamError, HfstTransducer};
+
+/// The transducers and metadata of a `.zhfst` speller archive.
+pub struct ZhfstArchive {
+    /// The locale of the speller, such as `se`, from the index.
+    pub locale: Option<String>,
+    /// The title of the speller, from the index.
+    pub title: Option<String>,
+    /// The acceptor: It accepts the correctly spelled words.
+    pub acceptor: HfstTransducer,
+    /// The error model: It maps misspelled words to their candidate
+    /// corrections, which are then checked against the acceptor.
+    pub error_model: HfstTransducer,
+}
+
+/// Errors from reading a `.zhfst` archive.
+#[derive(Debug, thiserror::Error)]
+pub enum ZhfstError {
+    /// Reading the archive failed.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The archive is not a valid zip file, or a file is missing from it.
+    #[error("Zip error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    /// The `index.xml` of the archive is not valid, or does not name an
+    /// acceptor and an error model.
+    #[error("Invalid index.xml: {0}")]
+    InvalidIndex(String),
+    /// A transducer file of the archive could not be read.
+    #[error("Could not read {0}: {1}")]
+    Stream(String, HfstInputStreamError),
+    /// A transducer file of the archive does not contain exactly one
+    /// transducer.
+    #[error("{0} does not contain exactly one transducer")]
+    NotOneTransducer(String),
+}
+
+/// The first child element of `parent` named `name`.
+fn child<'a, 'input>(
+    parent: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    parent.children().find(|node| node.has_tag_name(name))
+}
+
+impl ZhfstArchive {
+    /// Open the `.zhfst` archive at `path`, and read its transducers.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ZhfstError> {
+        Self::from_reader(std::fs::File::open(path)?)
+    }
+
+    /// Read a `.zhfst` archive from `reader`, and read its transducers.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self, ZhfstError> {
+        let mut archive = zip::ZipArchive::new(reader)?;
+
+        let mut index = String::new();
+        archive.by_name("index.xml")?.read_to_string(&mut index)?;
+        let index = roxmltree::Document::parse(&index)
+            .map_err(|e| ZhfstError::InvalidIndex(e.to_string()))?;
+        let root = index.root_element();
+        let info = child(root, "info");
+        let text = |name: &str| {
+            info.and_then(|info| child(info, name))
+                .and_then(|node| node.text())
+                .map(|text| text.trim().to_string())
+        };
+        let id = |name: &str| {
+            child(root, name)
+                .and_then(|node| node.attribute("id"))
+                .map(str::to_string)
+                .ok_or_else(|| ZhfstError::InvalidIndex(format!("no <{name} id=\"...\">")))
+        };
+        let locale = text("locale");
+        let title = text("title");
+        let acceptor_id = id("acceptor")?;
+        let error_model_id = id("errmodel")?;
+
+        let mut read_transducer = |id: String| -> Result<HfstTransducer, ZhfstError> {
+            let file = archive.by_name(&id)?;
+            let stream = HfstInputStream::from_reader(file)
+                .map_err(|e| ZhfstError::Stream(id.clone(), e))?;
+            stream
+                .read_only_transducer()
+                .ok_or(ZhfstError::NotOneTransducer(id))
+        };
+        let acceptor = read_transducer(acceptor_id)?;
+        let error_model = read_transducer(error_model_id)?;
+
+        Ok(Self {
+            locale,
+            title,
+            acceptor,
+            error_model,
+        })
+    }
+}